@@ -0,0 +1,27 @@
+//! Fuzz the parser extracting potential tests from Elm modules.
+//!
+//! Run with the parser corpus as seed:
+//! cargo +nightly fuzz run potential_tests corpus/potential_tests ../tests/parser-corpus
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The parser only depends on nom, so it is included directly
+// instead of turning elm-test-rs into a library.
+#[allow(dead_code)]
+#[path = "../../src/parser.rs"]
+mod parser;
+
+fuzz_target!(|source: &str| {
+    let potential_tests = parser::potential_tests(source);
+    // All found names are slices of the source
+    let slices = potential_tests.tests.iter().chain(&potential_tests.unexposed);
+    for slice in slices.chain(&potential_tests.unparsed) {
+        parser::position(source, slice);
+    }
+    for (name, _) in &potential_tests.other_types {
+        parser::position(source, name);
+    }
+    parser::imports(source);
+});
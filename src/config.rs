@@ -0,0 +1,240 @@
+//! Module dealing with the optional configuration of the project,
+//! in an elm-test-rs.json file or an "elm-test-rs" field of the elm.json, at the project root.
+//!
+//! The configuration gives default values to the command line arguments, with the same names,
+//! such as `{ "fuzz": 200, "report": "json", "watch-extra": ["tests/fixtures/**/*.json"] }`.
+//! Test files given as positional arguments are in a "files" list.
+
+use anyhow::Context;
+use clap::ArgMatches;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Name of the configuration file, next to elm.json.
+pub const FILE_NAME: &str = "elm-test-rs.json";
+
+/// Name of the configuration field in elm.json.
+const ELM_JSON_FIELD: &str = "elm-test-rs";
+
+/// Arguments that cannot be set in the configuration.
+const NOT_CONFIGURABLE: [&str; 6] = [
+    "project",
+    "elm-home",
+    "output",
+    "print-config",
+    "help",
+    "version",
+];
+
+#[derive(Debug, Default)]
+/// Configuration of the project.
+pub struct Config {
+    /// File the configuration was read from, if any.
+    pub path: Option<PathBuf>,
+    /// Values of the arguments, by name.
+    options: Map<String, Value>,
+}
+
+impl Config {
+    /// Read the configuration of the project, or use an empty one if there is none.
+    pub fn read(project_root: &Path) -> anyhow::Result<Config> {
+        let file_path = project_root.join(FILE_NAME);
+        let elm_json_path = project_root.join("elm.json");
+        let elm_json: Value = serde_json::from_str(
+            &std::fs::read_to_string(&elm_json_path).context("Unable to read elm.json")?,
+        )
+        .context("Invalid elm.json")?;
+        let in_elm_json = elm_json.get(ELM_JSON_FIELD);
+        let (path, options) = match (file_path.exists(), in_elm_json) {
+            (false, None) => return Ok(Config::default()),
+            (true, Some(_)) => anyhow::bail!(
+                "The configuration is both in {FILE_NAME} and the \"{ELM_JSON_FIELD}\" field of elm.json, please keep only one"
+            ),
+            (true, None) => {
+                let content = std::fs::read_to_string(&file_path)
+                    .context(format!("Failed to read {}", file_path.display()))?;
+                let json = serde_json::from_str(&content)
+                    .context(format!("Invalid JSON in {}", file_path.display()))?;
+                (file_path, json)
+            }
+            (false, Some(json)) => (elm_json_path, json.clone()),
+        };
+        match options {
+            Value::Object(options) => Ok(Config {
+                path: Some(path),
+                options,
+            }),
+            _ => anyhow::bail!("The configuration in {} must be an object", path.display()),
+        }
+    }
+
+    /// Convert the configuration into command line arguments,
+    /// to be validated and parsed exactly like the real ones.
+    pub fn to_args(&self) -> anyhow::Result<Vec<String>> {
+        let mut args = vec!["elm-test-rs".to_string()];
+        let mut files = Vec::new();
+        for (name, value) in &self.options {
+            let invalid = || format!("Invalid value for \"{name}\" in the configuration: {value}");
+            if NOT_CONFIGURABLE.contains(&name.as_str()) {
+                anyhow::bail!("\"{name}\" cannot be set in the configuration");
+            }
+            match (name.as_str(), value) {
+                ("files", Value::Array(values)) => {
+                    for file in values {
+                        files.push(file.as_str().with_context(invalid)?.to_string());
+                    }
+                }
+                ("verbose", Value::Number(n)) => {
+                    let count = n.as_u64().with_context(invalid)?;
+                    args.extend((0..count).map(|_| "-v".to_string()));
+                }
+                (_, Value::Bool(true)) => args.push(format!("--{name}")),
+                (_, Value::Bool(false)) => {}
+                (_, Value::String(s)) => args.extend([format!("--{name}"), s.clone()]),
+                (_, Value::Number(n)) => args.extend([format!("--{name}"), n.to_string()]),
+                (_, Value::Array(values)) => {
+                    for value in values {
+                        let value = value.as_str().with_context(invalid)?;
+                        args.extend([format!("--{name}"), value.to_string()]);
+                    }
+                }
+                _ => anyhow::bail!(invalid()),
+            }
+        }
+        if !files.is_empty() {
+            args.push("--".to_string());
+            args.extend(files);
+        }
+        Ok(args)
+    }
+}
+
+/// Arguments from the command line, falling back to the ones from the configuration.
+pub struct Settings<'a> {
+    pub cli: &'a ArgMatches<'a>,
+    pub config: &'a ArgMatches<'a>,
+    /// Directory against which relative paths of the configuration are resolved.
+    pub project_root: &'a Path,
+}
+
+impl<'a> Settings<'a> {
+    /// Whether the argument comes from the configuration instead of the command line.
+    pub fn is_from_config(&self, name: &str) -> bool {
+        self.cli.occurrences_of(name) == 0 && self.config.occurrences_of(name) > 0
+    }
+
+    fn matches(&self, name: &str) -> &ArgMatches<'a> {
+        if self.is_from_config(name) {
+            self.config
+        } else {
+            self.cli
+        }
+    }
+
+    pub fn is_present(&self, name: &str) -> bool {
+        self.matches(name).is_present(name)
+    }
+
+    pub fn occurrences_of(&self, name: &str) -> u64 {
+        self.matches(name).occurrences_of(name)
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        self.matches(name).value_of(name)
+    }
+
+    pub fn values_of(&self, name: &str) -> Vec<String> {
+        let values = self.matches(name).values_of(name);
+        values.into_iter().flatten().map(String::from).collect()
+    }
+
+    /// The effective configuration, in the same format as the configuration file.
+    pub fn effective_config(&self) -> Value {
+        let mut config = Map::new();
+        let value = |v: &str| v.parse().map_or(Value::from(v), Value::Number);
+        config.insert("verbose".into(), self.occurrences_of("verbose").into());
+        for flag in ["offline", "watch", "colocated", "strict"] {
+            config.insert(flag.into(), self.is_present(flag).into());
+        }
+        for name in [
+            "compiler",
+            "dependencies",
+            "report",
+            "compile-mode",
+            "seed",
+            "fuzz",
+            "workers",
+            "timeout",
+            "filter",
+            "runtime",
+            "node",
+            "deno-path",
+            "bun-path",
+        ] {
+            config.insert(name.into(), self.value_of(name).map_or(Value::Null, value));
+        }
+        for (key, name) in [
+            ("watch-extra", "watch-extra"),
+            ("watch-ignore", "watch-ignore"),
+            ("tests-dir", "tests-dir"),
+            ("ignore", "ignore"),
+            ("files", "PATH or GLOB"),
+        ] {
+            config.insert(key.into(), self.values_of(name).into());
+        }
+        Value::Object(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn to_args() {
+        let config = |json: serde_json::Value| Config {
+            path: None,
+            options: json.as_object().unwrap().clone(),
+        };
+        let args = config(serde_json::json!({
+            "files": ["tests/A.elm", "tests/B.elm"],
+            "fuzz": 200,
+            "offline": true,
+            "report": "json",
+            "verbose": 2,
+            "watch": false,
+            "watch-extra": ["*.json", "*.txt"],
+        }))
+        .to_args()
+        .unwrap();
+        assert_eq!(
+            args,
+            [
+                "elm-test-rs",
+                "--fuzz",
+                "200",
+                "--offline",
+                "--report",
+                "json",
+                "-v",
+                "-v",
+                "--watch-extra",
+                "*.json",
+                "--watch-extra",
+                "*.txt",
+                "--",
+                "tests/A.elm",
+                "tests/B.elm"
+            ]
+        );
+        assert!(config(serde_json::json!({ "project": "." }))
+            .to_args()
+            .is_err());
+        assert!(config(serde_json::json!({ "fuzz": null }))
+            .to_args()
+            .is_err());
+        assert!(config(serde_json::json!({ "files": [1] }))
+            .to_args()
+            .is_err());
+    }
+}
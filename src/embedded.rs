@@ -0,0 +1,259 @@
+//! Module running the tests in an embedded QuickJS engine,
+//! so that neither Node, Deno nor Bun needs to be installed.
+//!
+//! The kernel-patched `Runner.elm.js` and `Reporter.elm.js` each run
+//! in their own JS context and thread, exchanging messages with the supervisor.
+
+use anyhow::Context as _;
+use rquickjs::{Context, Ctx, Function};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::include_template;
+use crate::supervisor::{Event, Source, Spawner, Worker};
+
+/// Longest wait for a JS timer before checking if the thread was interrupted.
+const TIMER_SLICE: Duration = Duration::from_millis(50);
+
+/// Spawner of threads running the runners and reporter in the embedded engine.
+pub struct Threads {
+    runner: Arc<Sources>,
+    reporter: Arc<Sources>,
+}
+
+/// JS files evaluated in order by a thread, with their names.
+type Sources = Vec<(&'static str, String)>;
+
+impl Threads {
+    /// Read the kernel-patched `Runner.elm.js` and `Reporter.elm.js`,
+    /// as well as the templated `embedded_runner.js` and `embedded_reporter.js` glue code.
+    pub fn new(js_dir: &Path) -> anyhow::Result<Threads> {
+        let read = |names: [&'static str; 2]| -> anyhow::Result<Arc<Sources>> {
+            let read_one = |name: &'static str| {
+                std::fs::read_to_string(js_dir.join(name))
+                    .map(|source| (name, source))
+                    .context(format!("Failed to read {name}"))
+            };
+            Ok(Arc::new(
+                names
+                    .into_iter()
+                    .map(read_one)
+                    .collect::<anyhow::Result<_>>()?,
+            ))
+        };
+        Ok(Threads {
+            runner: read(["Runner.elm.js", "embedded_runner.js"])?,
+            reporter: read(["Reporter.elm.js", "embedded_reporter.js"])?,
+        })
+    }
+}
+
+impl Spawner for Threads {
+    fn spawn(&self, source: Source, events: &Sender<Event>) -> anyhow::Result<Box<dyn Worker>> {
+        let sources = match source {
+            Source::Reporter(_) => Arc::clone(&self.reporter),
+            Source::Runner(_) => Arc::clone(&self.runner),
+        };
+        let (to_thread, from_supervisor) = channel();
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let events = events.clone();
+        let thread_interrupted = Arc::clone(&interrupted);
+        let handle = std::thread::spawn(move || {
+            let result = thread_loop(
+                source,
+                &sources,
+                from_supervisor,
+                &events,
+                thread_interrupted,
+            );
+            let _ = events.send(Event::Stopped(
+                source,
+                result.err().map(|e| format!("{e:#}")),
+            ));
+        });
+        Ok(Box::new(Thread {
+            to_thread: Some(to_thread),
+            interrupted,
+            handle: Some(handle),
+        }))
+    }
+}
+
+/// A thread started by the Threads spawner.
+struct Thread {
+    to_thread: Option<Sender<String>>,
+    interrupted: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Worker for Thread {
+    fn send(&mut self, msg: &str) -> anyhow::Result<()> {
+        self.to_thread
+            .as_ref()
+            .context("The thread was stopped")?
+            .send(msg.to_string())
+            .context("The thread has stopped")
+    }
+
+    fn stop(&mut self) {
+        // Interrupt running JS code, and close the channel to end the thread loop
+        self.interrupted.store(true, Ordering::Relaxed);
+        self.to_thread = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Evaluate the JS sources and forward them all supervisor messages until the channel closes.
+fn thread_loop(
+    source: Source,
+    sources: &Sources,
+    from_supervisor: Receiver<String>,
+    events: &Sender<Event>,
+    interrupted: Arc<AtomicBool>,
+) -> anyhow::Result<()> {
+    let events = events.clone();
+    let engine = Engine::new(
+        move |msg| {
+            let _ = events.send(Event::Line(source, msg));
+        },
+        interrupted,
+    )?;
+    for (name, source) in sources {
+        engine.eval(name, source)?;
+    }
+    for msg in from_supervisor {
+        engine.receive(&msg)?;
+    }
+    Ok(())
+}
+
+/// A QuickJS context with the minimal environment needed by compiled Elm code.
+struct Engine {
+    runtime: rquickjs::Runtime,
+    context: Context,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Engine {
+    /// Create an engine whose JS `__send` function calls `send` with a JSON string.
+    /// JS code stops with an exception as soon as `interrupted` is set.
+    fn new(
+        send: impl Fn(String) + 'static,
+        interrupted: Arc<AtomicBool>,
+    ) -> anyhow::Result<Engine> {
+        let runtime = rquickjs::Runtime::new().context("Failed to create a QuickJS runtime")?;
+        let handler_interrupted = Arc::clone(&interrupted);
+        runtime.set_interrupt_handler(Some(Box::new(move || {
+            handler_interrupted.load(Ordering::Relaxed)
+        })));
+        let context = Context::full(&runtime).context("Failed to create a QuickJS context")?;
+        context
+            .with(|ctx| -> rquickjs::Result<()> {
+                let globals = ctx.globals();
+                globals.set("__send", Function::new(ctx.clone(), send)?)?;
+                globals.set(
+                    "__stdout",
+                    Function::new(ctx.clone(), |str: String| {
+                        print!("{str}");
+                        let _ = std::io::stdout().flush();
+                    })?,
+                )?;
+                globals.set(
+                    "__stderr",
+                    Function::new(ctx.clone(), |str: String| eprint!("{str}"))?,
+                )?;
+                Ok(())
+            })
+            .context("Failed to set up the QuickJS context")?;
+        let engine = Engine {
+            runtime,
+            context,
+            interrupted,
+        };
+        let prelude = include_template!("embedded_prelude.js").replacen(
+            "{{ polyfills }}",
+            include_template!("node_polyfills.js"),
+            1,
+        );
+        engine.eval("embedded_prelude.js", &prelude)?;
+        Ok(engine)
+    }
+
+    /// Evaluate a script, and everything it scheduled.
+    fn eval(&self, name: &str, source: &str) -> anyhow::Result<()> {
+        self.context
+            .with(|ctx| js_result(&ctx, ctx.eval::<(), _>(source)))
+            .context(format!("Failed to evaluate {name}"))?;
+        self.run_pending()
+    }
+
+    /// Pass a JSON message to the JS `__receive` function, and run everything it scheduled.
+    fn receive(&self, msg: &str) -> anyhow::Result<()> {
+        self.context
+            .with(|ctx| {
+                let receive: Function = js_result(&ctx, ctx.globals().get("__receive"))?;
+                js_result(&ctx, receive.call::<_, ()>((msg,)))
+            })
+            .context("Failed to process a message")?;
+        self.run_pending()
+    }
+
+    /// Run pending promise jobs and timers until there is nothing left to do,
+    /// or until the thread is interrupted, even while waiting for a timer.
+    fn run_pending(&self) -> anyhow::Result<()> {
+        loop {
+            self.check_interrupted()?;
+            while self.runtime.is_job_pending() {
+                if self.runtime.execute_pending_job().is_err() {
+                    anyhow::bail!("A pending JS job threw an exception");
+                }
+            }
+            let delay: i64 = self.context.with(|ctx| {
+                let run_timers: Function = js_result(&ctx, ctx.globals().get("__runTimers"))?;
+                js_result(&ctx, run_timers.call(()))
+            })?;
+            if delay < 0 {
+                return Ok(());
+            }
+            let mut remaining = Duration::from_millis(delay as u64);
+            while !remaining.is_zero() {
+                self.check_interrupted()?;
+                let slice = remaining.min(TIMER_SLICE);
+                std::thread::sleep(slice);
+                remaining -= slice;
+            }
+        }
+    }
+
+    fn check_interrupted(&self) -> anyhow::Result<()> {
+        if self.interrupted.load(Ordering::Relaxed) {
+            anyhow::bail!("Interrupted while waiting for pending JS jobs and timers");
+        }
+        Ok(())
+    }
+}
+
+/// Convert a QuickJS result, retrieving the message of thrown exceptions.
+fn js_result<T>(ctx: &Ctx, result: rquickjs::Result<T>) -> anyhow::Result<T> {
+    result.map_err(|err| match err {
+        rquickjs::Error::Exception => {
+            let thrown = ctx.catch();
+            match thrown.as_exception() {
+                Some(exception) => anyhow::anyhow!(
+                    "{}\n{}",
+                    exception.message().unwrap_or_default(),
+                    exception.stack().unwrap_or_default()
+                ),
+                None => anyhow::anyhow!("Uncaught JS value: {thrown:?}"),
+            }
+        }
+        err => anyhow::anyhow!("{err}"),
+    })
+}
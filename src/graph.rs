@@ -0,0 +1,132 @@
+//! Module dealing with the graph of imports between the Elm modules of a project.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+/// Imports between the Elm modules found in the source and test directories.
+pub struct ImportGraph {
+    /// Source and test directories.
+    dirs: Vec<PathBuf>,
+    /// For each module name, the files of modules importing it.
+    imported_by: HashMap<String, Vec<PathBuf>>,
+}
+
+impl ImportGraph {
+    /// Parse the imports of all Elm files within the given directories.
+    ///
+    /// Files that cannot be read or whose path is not a valid module name are ignored.
+    pub fn build<P: AsRef<Path>>(dirs: impl IntoIterator<Item = P>) -> ImportGraph {
+        let mut graph = ImportGraph::default();
+        for dir in dirs {
+            let dir = dir.as_ref();
+            graph.dirs.push(dir.to_path_buf());
+            for file in crate::make::elm_files_within(dir) {
+                if module_name(dir, &file).is_none() {
+                    log::debug!("No valid module name for {}", file.display());
+                    continue;
+                }
+                let Ok(source) = std::fs::read_to_string(&file) else {
+                    log::debug!("Failed to read {}", file.display());
+                    continue;
+                };
+                for imported in crate::parser::imports(&source) {
+                    graph
+                        .imported_by
+                        .entry(imported.to_string())
+                        .or_default()
+                        .push(file.clone());
+                }
+            }
+        }
+        graph
+    }
+
+    /// Return the files among `candidates` that are one of the `changed` files,
+    /// or that import one of them, directly or transitively.
+    ///
+    /// Changed files may not exist anymore, since only their path is needed.
+    pub fn affected<'a>(
+        &self,
+        changed: &[PathBuf],
+        candidates: &'a HashSet<PathBuf>,
+    ) -> HashSet<&'a PathBuf> {
+        let mut visited: HashSet<&Path> = HashSet::new();
+        let mut to_visit: Vec<&Path> = changed.iter().map(PathBuf::as_path).collect();
+        while let Some(file) = to_visit.pop() {
+            if !visited.insert(file) {
+                continue;
+            }
+            let name = self.dirs.iter().find_map(|dir| module_name(dir, file));
+            if let Some(importers) = name.and_then(|name| self.imported_by.get(&name)) {
+                to_visit.extend(importers.iter().map(PathBuf::as_path));
+            }
+        }
+        candidates
+            .iter()
+            .filter(|c| visited.contains(c.as_path()))
+            .collect()
+    }
+}
+
+/// Module name of a file within a source directory, such as "Foo.Bar" for "dir/Foo/Bar.elm".
+fn module_name(dir: &Path, file: &Path) -> Option<String> {
+    let trimmed = file.strip_prefix(dir).ok()?.with_extension("");
+    let parts: Vec<&str> = trimmed.iter().map(|s| s.to_str()).collect::<Option<_>>()?;
+    let valid = !parts.is_empty() && parts.iter().all(|s| crate::make::is_valid_module_name(s));
+    valid.then(|| parts.join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImportGraph;
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    #[test]
+    fn affected() {
+        let file = |path: &str| PathBuf::from(path);
+        let mut graph = ImportGraph {
+            dirs: vec![file("/src"), file("/tests")],
+            ..Default::default()
+        };
+        let mut import = |module: &str, by: &str| {
+            graph
+                .imported_by
+                .entry(module.to_string())
+                .or_default()
+                .push(file(by))
+        };
+        import("Foo", "/src/Foo/Bar.elm");
+        import("Foo.Bar", "/tests/BarTest.elm");
+        import("Foo", "/tests/FooTest.elm");
+        import("Foo.Baz", "/tests/Cycle.elm");
+        import("Cycle", "/src/Foo/Baz.elm");
+        let candidates: HashSet<PathBuf> = [
+            "/tests/BarTest.elm",
+            "/tests/FooTest.elm",
+            "/tests/Cycle.elm",
+        ]
+        .into_iter()
+        .map(file)
+        .collect();
+        let affected = |changed: &[&str]| {
+            let changed: Vec<PathBuf> = changed.iter().map(|c| file(c)).collect();
+            let mut affected: Vec<_> = graph
+                .affected(&changed, &candidates)
+                .into_iter()
+                .map(|p| p.to_str().unwrap().to_string())
+                .collect();
+            affected.sort();
+            affected
+        };
+        assert_eq!(
+            affected(&["/src/Foo.elm"]),
+            ["/tests/BarTest.elm", "/tests/FooTest.elm"]
+        );
+        assert_eq!(affected(&["/src/Foo/Bar.elm"]), ["/tests/BarTest.elm"]);
+        assert_eq!(affected(&["/tests/FooTest.elm"]), ["/tests/FooTest.elm"]);
+        assert_eq!(affected(&["/src/Foo/Baz.elm"]), ["/tests/Cycle.elm"]);
+        assert!(affected(&["/src/Unused.elm"]).is_empty());
+    }
+}
@@ -8,10 +8,12 @@ use std::path::Path;
 #[derive(Debug)]
 pub struct Options {
     pub compiler: String,
+    /// Directories of the test modules, relative to the project root.
+    pub tests_dirs: Vec<String>,
 }
 
 /// Add elm-explorations/test to test dependencies
-/// and initialize a template tests/Tests.elm file.
+/// and initialize a template Tests.elm file in the first tests directory.
 pub fn main<P: AsRef<Path>>(
     elm_home: P,
     project_root: P,
@@ -19,8 +21,10 @@ pub fn main<P: AsRef<Path>>(
     options: Options,
 ) -> anyhow::Result<()> {
     // Install elm-explorations/test in the tests dependencies
-    let project = Project::from_dir(project_root)?;
-    let elm_version = Project::elm_version(&project.config, &options.compiler)?;
+    let project = Project::from_dir(project_root, &options.tests_dirs)?;
+    let elm_version = Project::elm_version(&project.config, || {
+        crate::utils::elm_version_from_compiler(&options.compiler)
+    })?;
     let updated_config = crate::deps::init(elm_home, project.config, offline, elm_version)
         .context(
             "Something went wrong when installing elm-explorations/test to the tests dependencies",
@@ -28,15 +32,25 @@ pub fn main<P: AsRef<Path>>(
     crate::utils::json_write(project.root_directory.join("elm.json"), &updated_config)
         .context("Unable to write the updated elm.json")?;
 
-    // Create the tests/Tests.elm template
+    // Create the Tests.elm template in the first tests directory
     let init_tests_template = include_template!("Tests.elm");
-    let tests_dir = project.root_directory.join("tests");
-    std::fs::create_dir_all(&tests_dir).context("Impossible to create directory tests/")?;
+    let tests_dir = project
+        .tests_dirs
+        .first()
+        .context("There is no tests directory to create Tests.elm in")?;
+    std::fs::create_dir_all(tests_dir).context(format!(
+        "Impossible to create directory {}",
+        tests_dir.display()
+    ))?;
     let new_file_path = tests_dir.join("Tests.elm");
     if !new_file_path.exists() {
-        std::fs::write(new_file_path, init_tests_template)
+        std::fs::write(&new_file_path, init_tests_template)
             .context("Unable to create Tests.elm template")?;
-        log::error!("The file tests/Tests.elm was created");
+        let relative_path = new_file_path.strip_prefix(&project.root_directory);
+        log::error!(
+            "The file {} was created",
+            relative_path.unwrap_or(&new_file_path).display()
+        );
     }
     Ok(())
 }
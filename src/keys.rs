@@ -0,0 +1,206 @@
+//! Module reading the commands typed in watch mode.
+//!
+//! When the input is a terminal, it is put in raw mode (on Unix)
+//! so that each command is a single keypress, like in jest.
+//! Otherwise, such as on Windows or with a piped input,
+//! commands are read line by line, each followed by Enter.
+
+use std::io::{Read, Write};
+
+/// A command typed by the user in watch mode.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Rerun,
+    All,
+    Failed,
+    /// New test name filter, None to remove it.
+    Pattern(Option<String>),
+    /// New test module filter, None to remove it.
+    Module(Option<String>),
+    Seed,
+    Help,
+    Quit,
+    Unknown(String),
+}
+
+/// Terminal in raw mode until dropped, when its previous mode is restored.
+pub struct RawMode {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl RawMode {
+    /// Read keypresses one at a time, without echo, if the input is a terminal on Unix.
+    /// Ctrl-C does not interrupt the process anymore, and is read as the quit command.
+    pub fn enable() -> RawMode {
+        #[cfg(unix)]
+        {
+            use std::io::IsTerminal;
+            let original = std::io::stdin()
+                .is_terminal()
+                .then(|| {
+                    let mut termios = std::mem::MaybeUninit::uninit();
+                    // SAFETY: tcgetattr initializes termios when it succeeds.
+                    unsafe {
+                        (libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0)
+                            .then(|| termios.assume_init())
+                    }
+                })
+                .flatten();
+            if let Some(original) = original {
+                let mut raw = original;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+                raw.c_cc[libc::VMIN] = 1;
+                raw.c_cc[libc::VTIME] = 0;
+                // SAFETY: raw is a valid termios obtained from tcgetattr.
+                if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } == 0 {
+                    return RawMode {
+                        original: Some(original),
+                    };
+                }
+            }
+            RawMode { original: None }
+        }
+        #[cfg(not(unix))]
+        RawMode {}
+    }
+
+    /// Whether keypresses are read one at a time.
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(unix)]
+        return self.original.is_some();
+        #[cfg(not(unix))]
+        false
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: original is the valid termios obtained from tcgetattr.
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original) };
+        }
+    }
+}
+
+/// Read the commands typed by the user and pass them to `send`,
+/// until the input is closed or `send` returns false.
+pub fn read_commands(keypresses: bool, mut send: impl FnMut(Command) -> bool) {
+    let stdin = std::io::stdin();
+    let mut bytes = stdin.lock().bytes().map_while(Result::ok);
+    if keypresses {
+        while let Some(key) = bytes.next() {
+            let command = match key {
+                b'\r' | b'\n' => Command::Rerun,
+                b'a' => Command::All,
+                b'f' => Command::Failed,
+                b'p' => match prompt("Test name filter (empty to remove it): ", &mut bytes) {
+                    Some(pattern) => Command::Pattern(pattern),
+                    None => continue,
+                },
+                b'm' => match prompt("Test module filter (empty to remove it): ", &mut bytes) {
+                    Some(module) => Command::Module(module),
+                    None => continue,
+                },
+                b's' => Command::Seed,
+                b'h' => Command::Help,
+                // q, Ctrl-C and Ctrl-D
+                b'q' | 3 | 4 => Command::Quit,
+                _ => Command::Unknown(String::from_utf8_lossy(&[key]).into_owned()),
+            };
+            if !send(command) {
+                return;
+            }
+        }
+    } else {
+        let mut line = Vec::new();
+        for byte in bytes {
+            if byte != b'\n' {
+                line.push(byte);
+            } else if !send(parse_line(&String::from_utf8_lossy(&line))) {
+                return;
+            } else {
+                line.clear();
+            }
+        }
+    }
+}
+
+/// Parse a command typed on its own line, such as "p pattern".
+fn parse_line(line: &str) -> Command {
+    let line = line.trim();
+    let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+    let arg = Some(arg.trim().to_string()).filter(|a| !a.is_empty());
+    match command {
+        "" => Command::Rerun,
+        "a" => Command::All,
+        "f" => Command::Failed,
+        "p" => Command::Pattern(arg),
+        "m" => Command::Module(arg),
+        "s" => Command::Seed,
+        "h" => Command::Help,
+        "q" => Command::Quit,
+        _ => Command::Unknown(line.to_string()),
+    }
+}
+
+/// Read a text typed after a prompt, echoing it, until Enter.
+/// Returns None if cancelled with Escape or Ctrl-C, and Some(None) if the text is empty.
+fn prompt(message: &str, bytes: &mut impl Iterator<Item = u8>) -> Option<Option<String>> {
+    let mut stderr = std::io::stderr();
+    let _ = write!(stderr, "\n{message}");
+    let _ = stderr.flush();
+    let mut text: Vec<u8> = Vec::new();
+    for byte in bytes.by_ref() {
+        match byte {
+            b'\r' | b'\n' => {
+                let _ = writeln!(stderr);
+                let text = String::from_utf8_lossy(&text).trim().to_string();
+                return Some(Some(text).filter(|t| !t.is_empty()));
+            }
+            // Escape, Ctrl-C and Ctrl-D
+            27 | 3 | 4 => {
+                let _ = writeln!(stderr, "\nCancelled");
+                return None;
+            }
+            // Backspace and Delete remove the last character.
+            8 | 127 => {
+                while text.pop().is_some_and(|b| b & 0b1100_0000 == 0b1000_0000) {}
+                let _ = write!(stderr, "\u{8} \u{8}");
+            }
+            _ => {
+                text.push(byte);
+                let _ = stderr.write_all(&[byte]);
+            }
+        }
+        let _ = stderr.flush();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, prompt, Command};
+
+    #[test]
+    fn commands_on_their_own_line() {
+        assert_eq!(parse_line(""), Command::Rerun);
+        assert_eq!(
+            parse_line("p  some name "),
+            Command::Pattern(Some("some name".into()))
+        );
+        assert_eq!(parse_line("m"), Command::Module(None));
+        assert_eq!(parse_line("x"), Command::Unknown("x".into()));
+    }
+
+    #[test]
+    fn prompted_text() {
+        let typed = |s: &str| prompt("", &mut s.bytes());
+        assert_eq!(typed("abc\r"), Some(Some("abc".into())));
+        assert_eq!(typed("abd\u{7f}c\r"), Some(Some("abc".into())));
+        assert_eq!(typed("é\u{7f}e\r"), Some(Some("e".into())));
+        assert_eq!(typed("\r"), Some(None));
+        assert_eq!(typed("abc\u{1b}"), None);
+    }
+}
@@ -1,21 +1,192 @@
+mod config;
 mod deps;
+#[cfg(feature = "embedded")]
+mod embedded;
+mod graph;
 mod init;
 mod install;
+mod keys;
 mod logger;
 mod make;
 mod parser;
 mod project;
 mod run;
+mod runtime;
+mod supervisor;
 mod utils;
+mod workspace;
 
 use anyhow::Context;
 use clap::{App, AppSettings, Arg, SubCommand};
 use pubgrub_dependency_provider_elm::dependency_provider::VersionStrategy;
 use std::io::IsTerminal;
 use std::num::NonZeroU32;
+use std::path::Path;
 
 /// Main entry point of elm-test-rs.
 fn main() -> anyhow::Result<()> {
+    let matches = cli().get_matches();
+
+    // Retrieve the path to the elm home.
+    let elm_home = match matches.value_of("elm-home") {
+        None => utils::elm_home().context("Elm home not found")?,
+        Some(str_path) => {
+            // Create the path to make sure it exists.
+            std::fs::create_dir_all(str_path)
+                .context(format!("{str_path} does not exist and is not writable"))?;
+            utils::absolute_path(str_path)?
+        }
+    };
+
+    // Make or run the tests of multiple projects.
+    let projects: Vec<&str> = matches.values_of("project").unwrap().collect(); // unwrap is fine since project has a default value
+    if matches.is_present("workspace") || projects.len() > 1 {
+        let cli = matches.subcommand_matches("make").unwrap_or(&matches);
+        logger::init(cli.occurrences_of("verbose")).context("Failed to initialize logger")?;
+        let exit_code = workspace_main(&matches, &elm_home, &projects)?;
+        std::process::exit(exit_code);
+    }
+
+    // Retrieve the path to the project root directory.
+    let elm_project_root = utils::elm_project_root(matches.value_of("project").unwrap())?; // unwrap is fine since project has a default value
+
+    // Read the configuration of the project, parsed like arguments which override it.
+    let config = config::Config::read(&elm_project_root)?;
+    let config_matches = parse_config(&config)?;
+    let settings = config::Settings {
+        cli: matches.subcommand_matches("make").unwrap_or(&matches),
+        config: &config_matches,
+        project_root: &elm_project_root,
+    };
+
+    // Set log verbosity.
+    let verbosity = settings.occurrences_of("verbose");
+    logger::init(verbosity).context("Failed to initialize logger")?;
+
+    // Show the effective configuration.
+    if settings.cli.is_present("print-config") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&settings.effective_config())?
+        );
+        return Ok(());
+    }
+
+    match matches.subcommand() {
+        ("init", Some(_)) => {
+            let init_options = get_init_options(&settings)?;
+            let offline = settings.is_present("offline");
+            init::main(elm_home, elm_project_root, offline, init_options)
+        }
+        ("install", Some(sub_matches)) => {
+            let packages: Vec<String> = sub_matches
+                .values_of("PACKAGE")
+                .into_iter()
+                .flatten()
+                .map(|s| s.to_string())
+                .collect();
+            install::main(packages)
+        }
+        ("make", Some(_)) => {
+            let exit_code = make::main(&elm_home, &elm_project_root, get_make_options(&settings)?)?;
+            std::process::exit(exit_code);
+        }
+        _ => {
+            let make_options = get_make_options(&settings)?;
+            let run_options = get_run_options(&settings)?;
+            let exit_code = run::main(&elm_home, &elm_project_root, make_options, run_options)?;
+            std::process::exit(exit_code);
+        }
+    }
+}
+
+/// Parse the configuration exactly like command line arguments.
+fn parse_config(config: &config::Config) -> anyhow::Result<clap::ArgMatches<'static>> {
+    cli()
+        .get_matches_from_safe(config.to_args()?)
+        .map_err(|err| {
+            let path = config
+                .path
+                .as_deref()
+                .unwrap_or(Path::new(config::FILE_NAME));
+            // Keep the error and hints, but not the usage of the command line
+            let message = err.message.split("\n\n").next().unwrap_or_default();
+            anyhow::anyhow!("Invalid configuration in {}\n{message}", path.display())
+        })
+}
+
+/// Make or run the tests of multiple projects, each with its own configuration.
+/// The projects are the ones given with --project, or all the ones found within them with --workspace.
+fn workspace_main(
+    matches: &clap::ArgMatches,
+    elm_home: &Path,
+    projects: &[&str],
+) -> anyhow::Result<i32> {
+    let cli = matches.subcommand_matches("make").unwrap_or(matches);
+    if cli.occurrences_of("PATH or GLOB") > 0 {
+        anyhow::bail!("Test files cannot be given as arguments with multiple projects");
+    }
+    let mut roots = Vec::new();
+    for project in projects {
+        if matches.is_present("workspace") {
+            roots.extend(workspace::discover(Path::new(project))?);
+        } else {
+            roots.push(utils::elm_project_root(project)?);
+        }
+    }
+    roots.sort();
+    roots.dedup();
+
+    // Read the options of each project.
+    let mut members = Vec::new();
+    let mut effective_configs = serde_json::Map::new();
+    for root in roots {
+        let config = config::Config::read(&root)?;
+        let config_matches = parse_config(&config)?;
+        let settings = config::Settings {
+            cli,
+            config: &config_matches,
+            project_root: &root,
+        };
+        let key = root.display().to_string();
+        effective_configs.insert(key, settings.effective_config());
+        let make_options = get_make_options(&settings)?;
+        let run_options = get_run_options(&settings)?;
+        let member = workspace::Member {
+            root,
+            make_options,
+            run_options,
+        };
+        if member.has_tests_dir() {
+            members.push(member);
+        } else {
+            log::warn!(
+                "Skipping {} which has no tests directory",
+                member.root.display()
+            );
+        }
+    }
+    if members.is_empty() {
+        anyhow::bail!("No Elm project with a tests directory was found");
+    }
+
+    // Show the effective configuration of each project.
+    if cli.is_present("print-config") {
+        println!("{}", serde_json::to_string_pretty(&effective_configs)?);
+        return Ok(0);
+    }
+
+    match matches.subcommand() {
+        ("make", Some(_)) => workspace::make(elm_home, &members),
+        ("init", _) | ("install", _) => {
+            anyhow::bail!("init and install are not supported with multiple projects")
+        }
+        _ => workspace::run(elm_home, &members),
+    }
+}
+
+/// Command line interface of elm-test-rs.
+fn cli() -> App<'static, 'static> {
     // Arguments available to all subcommands.
     let global_args = vec![
         Arg::with_name("elm-home")
@@ -29,8 +200,14 @@ fn main() -> anyhow::Result<()> {
             .long("project")
             .global(true)
             .default_value(".")
+            .multiple(true)
+            .number_of_values(1)
             .value_name("path")
-            .help("Path to the root directory of the project"),
+            .help("Path to the root directory of the project. Can be used multiple times to test multiple projects"),
+        Arg::with_name("workspace")
+            .long("workspace")
+            .global(true)
+            .help("Test all the Elm projects with a tests directory found within the project path(s)"),
         Arg::with_name("offline")
             .long("offline")
             .global(true)
@@ -40,12 +217,30 @@ fn main() -> anyhow::Result<()> {
             .multiple(true)
             .global(true)
             .help("Increase verbosity. Can be used multiple times -vvv"),
+        Arg::with_name("print-config")
+            .long("print-config")
+            .global(true)
+            .help("Print the effective configuration, from elm-test-rs.json (or elm.json) and the arguments"),
     ];
     // Arguments shared with the "make" subcommand.
     let make_args = vec![
         Arg::with_name("watch")
             .long("watch")
             .help("Rerun tests on file changes"),
+        Arg::with_name("watch-extra")
+            .long("watch-extra")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("glob")
+            .help("Also rerun tests in watch mode when files matching this glob change, such as tests/fixtures/**/*.json"),
+        Arg::with_name("watch-ignore")
+            .long("watch-ignore")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("glob")
+            .help("Never rerun tests in watch mode when files matching this glob change, such as src/Generated/**"),
         Arg::with_name("compiler")
             .long("compiler")
             .default_value("elm")
@@ -72,9 +267,35 @@ fn main() -> anyhow::Result<()> {
             .value_name("output_path")
             .possible_values(&["/dev/null"])
             .help("This argument is ignored, and only present for compatibility with `elm make --output=/dev/null` for the make subcommand"),
+        Arg::with_name("compile-mode")
+            .long("compile-mode")
+            .default_value("default")
+            .possible_values(&["default", "optimize", "debug"])
+            .help("Compile the tests with the --optimize or --debug flag of elm make. The Debug module cannot be used with optimize"),
+        Arg::with_name("tests-dir")
+            .long("tests-dir")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .default_value("tests")
+            .value_name("path")
+            .help("Directory of test modules, relative to the project root. Can be used multiple times"),
+        Arg::with_name("colocated")
+            .long("colocated")
+            .help("Also look for test modules in the source directories: files ending with Test.elm, or modules importing Test and exposing values annotated as Test"),
+        Arg::with_name("strict")
+            .long("strict")
+            .help("Fail if a value annotated as Test is not exposed by its module"),
+        Arg::with_name("ignore")
+            .long("ignore")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("glob")
+            .help("Exclude the test files matching this glob, relative to the project root, such as tests/Helpers/**"),
         Arg::with_name("PATH or GLOB")
             .multiple(true)
-            .help("Path to a test module, or glob pattern such as tests/*.elm")
+            .help("Path to a test module, or glob pattern such as tests/*.elm. Patterns starting with ! exclude files, such as '!tests/Helpers/**'. Select a single test value with tests/Module.elm::suite")
     ];
     let run_args = vec![
         Arg::with_name("seed")
@@ -91,16 +312,47 @@ fn main() -> anyhow::Result<()> {
             .takes_value(true)
             .value_name("N")
             .help("Number of worker threads [default: <number of logic cores>]"),
+        Arg::with_name("timeout")
+            .long("timeout")
+            .takes_value(true)
+            .value_name("seconds")
+            .help("Stop the tests if a single test runs for longer than this [default: no limit]"),
         Arg::with_name("filter")
             .long("filter")
             .takes_value(true)
             .value_name("string")
             .help("Keep only tests whose description contains the given string"),
+        Arg::with_name("runtime")
+            .long("runtime")
+            .takes_value(true)
+            .possible_values(if cfg!(feature = "embedded") {
+                &["node", "deno", "bun", "embedded"]
+            } else {
+                &["node", "deno", "bun"]
+            })
+            .help("JavaScript runtime used to run the tests [default: node, or another one if node is not found]"),
+        Arg::with_name("node")
+            .long("node")
+            .default_value("node")
+            .value_name("path")
+            .help("Use a custom path to a Node executable"),
+        Arg::with_name("deno-path")
+            .long("deno-path")
+            .default_value("deno")
+            .value_name("path")
+            .help("Use a custom path to a Deno executable"),
+        Arg::with_name("bun-path")
+            .long("bun-path")
+            .default_value("bun")
+            .value_name("path")
+            .help("Use a custom path to a Bun executable"),
         Arg::with_name("deno")
             .long("deno")
-            .help("Rerun tests with Deno instead of Node"),
+            .hidden(true)
+            .conflicts_with("runtime")
+            .help("Deprecated alias of --runtime deno"),
     ];
-    let matches = App::new("elm-test-rs")
+    App::new("elm-test-rs")
         .version(std::env!("CARGO_PKG_VERSION"))
         .args(&global_args)
         .args(&make_args)
@@ -126,64 +378,13 @@ fn main() -> anyhow::Result<()> {
                 .args(&make_args)
                 .setting(AppSettings::DisableVersion),
         )
-        .get_matches();
-
-    // Retrieve the path to the elm home.
-    let elm_home = match matches.value_of("elm-home") {
-        None => utils::elm_home().context("Elm home not found")?,
-        Some(str_path) => {
-            // Create the path to make sure it exists.
-            std::fs::create_dir_all(str_path)
-                .context(format!("{str_path} does not exist and is not writable"))?;
-            utils::absolute_path(str_path)?
-        }
-    };
-
-    // Retrieve the path to the project root directory.
-    let elm_project_root = utils::elm_project_root(matches.value_of("project").unwrap())?; // unwrap is fine since project has a default value
-
-    // Set log verbosity.
-    let verbosity = matches.occurrences_of("verbose");
-    logger::init(verbosity).context("Failed to initialize logger")?;
-
-    match matches.subcommand() {
-        ("init", Some(sub_matches)) => {
-            let init_options = get_init_options(&matches)?;
-            init::main(
-                elm_home,
-                elm_project_root,
-                sub_matches.is_present("offline"),
-                init_options,
-            )
-        }
-        ("install", Some(sub_matches)) => {
-            let packages: Vec<String> = sub_matches
-                .values_of("PACKAGE")
-                .into_iter()
-                .flatten()
-                .map(|s| s.to_string())
-                .collect();
-            install::main(packages)
-        }
-        ("make", Some(sub_matches)) => {
-            let exit_code =
-                make::main(&elm_home, &elm_project_root, get_make_options(sub_matches)?)?;
-            std::process::exit(exit_code);
-        }
-        _ => {
-            let make_options = get_make_options(&matches)?;
-            let run_options = get_run_options(&matches)?;
-            let exit_code = run::main(&elm_home, &elm_project_root, make_options, run_options)?;
-            std::process::exit(exit_code);
-        }
-    }
 }
 
 /// Retrieve options related to the make subcommand.
-fn get_make_options(arg_matches: &clap::ArgMatches) -> anyhow::Result<make::Options> {
+fn get_make_options(settings: &config::Settings) -> anyhow::Result<make::Options> {
     let connectivity = match (
-        arg_matches.is_present("offline"),
-        arg_matches.value_of("dependencies"),
+        settings.is_present("offline"),
+        settings.value_of("dependencies"),
     ) {
         (false, None) => deps::ConnectivityStrategy::Progressive,
         (true, None) => deps::ConnectivityStrategy::Offline,
@@ -193,88 +394,162 @@ fn get_make_options(arg_matches: &clap::ArgMatches) -> anyhow::Result<make::Opti
         (false, Some(_)) => anyhow::bail!("Invalid --dependencies value"),
     };
 
-    let compiler = get_compiler(arg_matches)?;
+    let compiler = get_compiler(settings)?;
+    let compiler_version = utils::elm_version_from_compiler(&compiler)?;
 
-    let report = match arg_matches.value_of("report").unwrap() {
+    let report = match settings.value_of("report").unwrap() {
         // unwrap is fine since there is a default value
         "json" => String::from("json"),
         _ => String::from("console"),
     };
 
-    let files: Vec<String> = arg_matches
-        .values_of("PATH or GLOB")
-        .into_iter()
-        .flatten()
-        .map(|s| s.to_string())
-        .collect();
+    let compile_mode = match settings.value_of("compile-mode").unwrap() {
+        // unwrap is fine since there is a default value
+        "optimize" => make::CompileMode::Optimize,
+        "debug" => make::CompileMode::Debug,
+        _ => make::CompileMode::Default,
+    };
+
+    // Relative paths from the configuration are relative to the project root.
+    let mut files = settings.values_of("PATH or GLOB");
+    if settings.is_from_config("PATH or GLOB") {
+        let root = settings.project_root;
+        let join = |f: &str| root.join(f).to_string_lossy().into_owned();
+        files = files
+            .iter()
+            .map(|f| match f.strip_prefix('!') {
+                Some(negated) => format!("!{}", join(negated)),
+                None => join(f),
+            })
+            .collect();
+    }
     Ok(make::Options {
-        verbosity: arg_matches.occurrences_of("verbose"),
-        watch: arg_matches.is_present("watch"),
+        verbosity: settings.occurrences_of("verbose"),
+        watch: settings.is_present("watch"),
+        tests_dirs: settings.values_of("tests-dir"),
+        colocated: settings.is_present("colocated"),
+        strict: settings.is_present("strict"),
+        watch_extra: settings.values_of("watch-extra"),
+        watch_ignore: settings.values_of("watch-ignore"),
         compiler,
+        compiler_version,
         connectivity,
         files,
+        ignore: settings.values_of("ignore"),
         report,
+        compile_mode,
     })
 }
 
 /// Retrieve options related to the main run command.
-fn get_run_options(arg_matches: &clap::ArgMatches) -> anyhow::Result<run::Options> {
+fn get_run_options(settings: &config::Settings) -> anyhow::Result<run::Options> {
     // Use nanoseconds of current time as seed.
     let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
-    let seed: u32 = match arg_matches.value_of("seed") {
+    let seed: u32 = match settings.value_of("seed") {
         None => now.unwrap().as_nanos() as u32,
         Some(str_seed) => str_seed.parse().context("Invalid --seed value")?,
     };
-    let str_fuzz = arg_matches.value_of("fuzz").unwrap(); // unwrap is fine since there is a default value
+    let str_fuzz = settings.value_of("fuzz").unwrap(); // unwrap is fine since there is a default value
     let fuzz: NonZeroU32 = str_fuzz
         .parse()
         .context("Invalid --fuzz value. It must be a positive integer.")?;
 
-    let workers: u32 = match arg_matches.value_of("workers") {
+    let workers: u32 = match settings.value_of("workers") {
         None => num_cpus::get() as u32,
         Some(str_workers) => str_workers.parse().context("Invalid --workers value")?,
     };
 
-    let reporter = match arg_matches.value_of("report").unwrap() {
+    let timeout = match settings.value_of("timeout") {
+        None => None,
+        Some(str_timeout) => Some(std::time::Duration::from_secs_f64(
+            str_timeout
+                .parse()
+                .ok()
+                .filter(|secs: &f64| secs.is_finite() && *secs > 0.0)
+                .context("Invalid --timeout value. It must be a positive number of seconds.")?,
+        )),
+    };
+
+    let reporter = match settings.value_of("report").unwrap() {
         // unwrap is fine since there is a default value
         "console" => String::from(console_color_mode()),
         r => String::from(r),
     };
 
-    let runtime = if arg_matches.is_present("deno") {
-        run::Runtime::Deno
-    } else {
-        run::Runtime::Node
+    // A custom path to a runtime means we should not fall back to another runtime.
+    let custom_paths: Vec<runtime::Runtime> = [
+        ("node", runtime::Runtime::Node),
+        ("deno-path", runtime::Runtime::Deno),
+        ("bun-path", runtime::Runtime::Bun),
+    ]
+    .into_iter()
+    .filter(|(arg_name, _)| settings.occurrences_of(arg_name) > 0)
+    .map(|(_, runtime)| runtime)
+    .collect();
+    let runtime = match settings.value_of("runtime") {
+        None if settings.is_present("deno") => {
+            log::error!("Warning: --deno is deprecated, use --runtime deno instead");
+            Some(runtime::Runtime::Deno)
+        }
+        None => match custom_paths.as_slice() {
+            [] => None,
+            [runtime] => Some(*runtime),
+            _ => anyhow::bail!(
+                "--node, --deno-path and --bun-path select different runtimes, use --runtime to choose one"
+            ),
+        },
+        Some("deno") => Some(runtime::Runtime::Deno),
+        Some("bun") => Some(runtime::Runtime::Bun),
+        Some("embedded") => Some(runtime::Runtime::Embedded),
+        Some(_) => Some(runtime::Runtime::Node),
+    };
+    let executables = runtime::Executables {
+        node: get_executable(settings, "node")?,
+        deno: get_executable(settings, "deno-path")?,
+        bun: get_executable(settings, "bun-path")?,
     };
     Ok(run::Options {
         seed,
         fuzz,
         workers,
-        filter: arg_matches.value_of("filter").map(|s| s.to_string()),
+        filter: settings.value_of("filter").map(|s| s.to_string()),
         reporter,
         runtime,
+        executables,
+        timeout,
     })
 }
 
 /// Retrieve options related to the init command.
-fn get_init_options(arg_matches: &clap::ArgMatches) -> anyhow::Result<init::Options> {
+fn get_init_options(settings: &config::Settings) -> anyhow::Result<init::Options> {
     Ok(init::Options {
-        compiler: get_compiler(arg_matches)?,
+        compiler: get_compiler(settings)?,
+        tests_dirs: settings.values_of("tests-dir"),
     })
 }
 
-/// Retrieve the path to the Elm compiler, resolving relative paths to absolute
+/// Retrieve the path to the Elm compiler.
+fn get_compiler(settings: &config::Settings) -> anyhow::Result<String> {
+    get_executable(settings, "compiler")
+}
+
+/// Retrieve the path to an executable, resolving relative paths to absolute
 /// ones (a bare command such as "elm" is left as-is to be looked up in PATH).
-fn get_compiler(arg_matches: &clap::ArgMatches) -> anyhow::Result<String> {
-    let compiler = arg_matches.value_of("compiler").unwrap(); // unwrap is fine since compiler has a default value
-    let compiler_path = std::path::Path::new(compiler);
-    if compiler_path.components().count() > 1 {
-        Ok(utils::absolute_path(compiler_path)?
+/// Relative paths from the configuration are relative to the project root.
+fn get_executable(settings: &config::Settings, arg_name: &str) -> anyhow::Result<String> {
+    let executable = settings.value_of(arg_name).unwrap(); // unwrap is fine since all executables have a default value
+    let executable_path = Path::new(executable);
+    if executable_path.components().count() > 1 {
+        let executable_path = match settings.is_from_config(arg_name) {
+            true => settings.project_root.join(executable_path),
+            false => executable_path.to_path_buf(),
+        };
+        Ok(utils::absolute_path(executable_path)?
             .to_str()
             .context("Could not convert to &str")?
             .to_string())
     } else {
-        Ok(compiler.to_string())
+        Ok(executable.to_string())
     }
 }
 
@@ -1,9 +1,10 @@
 //! Module dealing with compiling the test code.
 
 use anyhow::Context;
-use glob::glob;
+use glob::{glob, Pattern};
+use pubgrub::version::SemanticVersion;
 use pubgrub_dependency_provider_elm::project_config::ProjectConfig;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs;
 use std::io::Write;
@@ -11,20 +12,62 @@ use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use walkdir::WalkDir;
 
+use crate::graph::ImportGraph;
 use crate::include_template;
-use crate::project::Project;
+use crate::project::{Project, Selection, WatchFilter};
+use crate::utils::Cancel;
 
 #[derive(Debug)]
 /// Options passed as arguments.
 pub struct Options {
     pub verbosity: u64,
     pub watch: bool,
+    /// Glob patterns of additional files triggering a rerun in watch mode.
+    pub watch_extra: Vec<String>,
+    /// Glob patterns of files never triggering a rerun in watch mode.
+    pub watch_ignore: Vec<String>,
     pub compiler: String,
+    /// Version of the compiler, probed once with `--version` for the whole invocation.
+    pub compiler_version: SemanticVersion,
     pub connectivity: crate::deps::ConnectivityStrategy,
+    /// Paths or globs of the test modules, excluded if starting with `!`.
+    /// A top level value can be selected with a `::value` suffix.
     pub files: Vec<String>,
+    /// Glob patterns of test modules to exclude, relative to the project root.
+    pub ignore: Vec<String>,
+    /// Directories of the test modules, relative to the project root.
+    pub tests_dirs: Vec<String>,
+    /// Also look for test modules colocated with the code in the source directories.
+    pub colocated: bool,
+    /// Fail instead of warning for values annotated as `Test` that are not exposed.
+    pub strict: bool,
     pub report: String,
+    pub compile_mode: CompileMode,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Compilation mode of the tests, with the flags of `elm make`.
+pub enum CompileMode {
+    Default,
+    Optimize,
+    Debug,
+}
+
+impl CompileMode {
+    /// The `elm make` flag of this compilation mode, if any.
+    pub fn flag(self) -> Option<&'static str> {
+        match self {
+            CompileMode::Default => None,
+            CompileMode::Optimize => Some("--optimize"),
+            CompileMode::Debug => Some("--debug"),
+        }
+    }
+}
+
+/// Name of the file next to Runner.elm.js with the key of the inputs it was compiled from,
+/// followed by the kernel patch applied to it, if any.
+pub const RUNNER_KEY_FILE_NAME: &str = "Runner.elm.js.key";
+
 /// Main function, generating and compiling a Runner.elm file.
 /// It has multiple steps that can be summarized as:
 ///
@@ -41,14 +84,22 @@ pub fn main(elm_home: &Path, elm_project_root: &Path, options: Options) -> anyho
     );
     log::warn!("\n{}\n{}\n", title, "-".repeat(title.len()));
 
-    let mut project = Project::from_dir(elm_project_root)?;
+    let mut project = Project::from_dir(elm_project_root, &options.tests_dirs)?;
     if options.watch {
-        project.watch(|proj| main_helper(elm_home, proj, &options).map(|_| ()))?;
+        let filter = WatchFilter::new(
+            &project.root_directory,
+            &options.watch_extra,
+            &options.watch_ignore,
+        )?;
+        project.watch(&filter, |proj, selection, cancel| {
+            main_helper(elm_home, proj, &options, selection, cancel).map(|_| ())
+        })?;
         Ok(0)
     } else {
-        match main_helper(elm_home, &project, &options)? {
+        let selection = Selection::default();
+        match main_helper(elm_home, &project, &options, &selection, &Cancel::default())? {
             Output::MakeFailure => Ok(1),
-            Output::MakeSuccess { .. } => Ok(0),
+            Output::MakeSuccess { .. } | Output::NoSelectedTests => Ok(0),
         }
     }
 }
@@ -56,6 +107,8 @@ pub fn main(elm_home: &Path, elm_project_root: &Path, options: Options) -> anyho
 /// Output of running "elm make" on all the tests files.
 pub enum Output {
     MakeFailure,
+    /// In watch mode, no test module is selected by the changed files or the module filter.
+    NoSelectedTests,
     MakeSuccess {
         tests_root: PathBuf,
         modules_abs_paths: HashSet<PathBuf>,
@@ -65,36 +118,194 @@ pub enum Output {
 
 /// Do main stuff and outputs the paths to the tests directories
 /// (useful for watch mode).
+///
+/// In watch mode, only the selected test modules are compiled:
+/// those importing the changed files (directly or not) and matching the module filter.
+/// Compilation is stopped early if `cancel` is raised.
 pub fn main_helper(
     elm_home: &Path,
     project: &Project,
     options: &Options,
+    selection: &Selection,
+    cancel: &Cancel,
 ) -> anyhow::Result<Output> {
+    match generate(elm_home, project, options, selection, cancel)? {
+        Generated::Done(output) => Ok(output),
+        Generated::Runner(runner) => {
+            let command = runner.compile(elm_home, options, cancel)?;
+            runner.finish(&command, options)
+        }
+    }
+}
+
+/// Result of the generation of Runner.elm.
+pub enum Generated {
+    /// There is nothing to compile, because no test is selected
+    /// or Runner.elm.js was already compiled from the same inputs.
+    Done(Output),
+    /// Runner.elm must be compiled.
+    Runner(Runner),
+}
+
+/// A generated Runner.elm, ready to be compiled.
+pub struct Runner {
+    pub tests_root: PathBuf,
+    modules_abs_paths: HashSet<PathBuf>,
+    compiled_runner: PathBuf,
+    key_file: PathBuf,
+    key: String,
+}
+
+impl Runner {
+    /// Compile the src/Runner.elm file into Runner.elm.js
+    pub fn compile(
+        &self,
+        elm_home: &Path,
+        options: &Options,
+        cancel: &Cancel,
+    ) -> anyhow::Result<std::process::Output> {
+        log::info!("Compiling the generated templated src/Runner.elm ...");
+        // Capture compiler output if --report=json,
+        // or to recognize the errors due to the Debug module with --compile-mode=optimize.
+        let capture_stderr =
+            options.report == "json" || options.compile_mode == CompileMode::Optimize;
+        compile(
+            elm_home,
+            &self.tests_root,      // current_dir
+            &options.compiler,     // compiler
+            &self.compiled_runner, // output
+            &options.report,       // report
+            options.compile_mode,  // mode
+            [Path::new("src").join("Runner.elm")],
+            capture_stderr,
+            cancel,
+        )
+    }
+
+    /// Record the key of the inputs of a successful compilation,
+    /// or report the errors of a failed one.
+    pub fn finish(
+        self,
+        command: &std::process::Output,
+        options: &Options,
+    ) -> anyhow::Result<Output> {
+        if command.status.success() {
+            log::warn!("✓ Compilation of tests modules succeeded");
+            fs::write(&self.key_file, &self.key)
+                .context("Failed to write the key of Runner.elm.js")?;
+            Ok(Output::MakeSuccess {
+                tests_root: self.tests_root,
+                modules_abs_paths: self.modules_abs_paths,
+                compiled_runner: self.compiled_runner,
+            })
+        } else {
+            report_failure(command, &options.report)?;
+            let debug_remnants =
+                String::from_utf8_lossy(&command.stderr).contains("DEBUG REMNANTS");
+            if options.compile_mode == CompileMode::Optimize && debug_remnants {
+                log::error!(
+                    "The tests cannot be compiled with --compile-mode=optimize because they use the Debug module.\n\
+                     Remove the calls to Debug.log, Debug.toString and Debug.todo, or use another compilation mode."
+                );
+            }
+            Ok(Output::MakeFailure)
+        }
+    }
+}
+
+/// Generate the elm.json and Runner.elm of the selected tests,
+/// unless Runner.elm.js was already compiled from the same inputs.
+pub fn generate(
+    elm_home: &Path,
+    project: &Project,
+    options: &Options,
+    selection: &Selection,
+    cancel: &Cancel,
+) -> anyhow::Result<Generated> {
     let start_time = std::time::Instant::now();
 
-    let modules_abs_paths = if options.files.is_empty() {
-        // Default with elm modules in the tests/ directory
-        elm_files_within(project.root_directory.join("tests"))
+    let (negated, files): (Vec<String>, Vec<String>) = options
+        .files
+        .iter()
+        .cloned()
+        .partition(|f| f.starts_with('!'));
+    let mut selected_values = HashMap::new();
+    let mut modules_abs_paths = if files.is_empty() {
+        // Default with elm modules in the tests directories
+        let mut paths: HashSet<PathBuf> = project
+            .tests_dirs
+            .iter()
+            .flat_map(test_files_within)
             .map(crate::utils::absolute_path)
-            .collect::<Result<_, _>>()?
+            .collect::<Result<_, _>>()?;
+        if options.colocated {
+            paths.extend(colocated_tests(project));
+        }
+        paths
     } else {
         // Get file paths of all modules in canonical form (absolute path)
-        get_elm_modules_abs_paths(&options.files)?
+        let (paths, values) = get_selected_modules(&files)?;
+        selected_values = values;
+        paths
     };
 
+    // Remove the modules excluded by --ignore or negated patterns.
+    let found_count = modules_abs_paths.len();
+    let excluded = excluded_patterns(&project.root_directory, &options.ignore, &negated)?;
+    modules_abs_paths.retain(|path| !is_excluded(&excluded, path));
+    if found_count > 0 && modules_abs_paths.is_empty() {
+        anyhow::bail!(
+            "All the {found_count} test files found are excluded by --ignore or negated patterns"
+        );
+    }
+
     // Report an error if no file was found.
     if modules_abs_paths.is_empty() {
-        if options.files.is_empty() {
-            anyhow::bail!("No file was found in your tests/ directory. You can create one with: elm-test-rs init");
+        if files.is_empty() {
+            anyhow::bail!(
+                "No file was found in your tests directories ({}). You can create one with: elm-test-rs init",
+                options.tests_dirs.join(", ")
+            );
         } else {
             anyhow::bail!(
                 "No file was found matching your pattern: {}",
-                options.files.join(" ")
+                files.join(" ")
             );
         }
     }
 
-    let elm_version = Project::elm_version(&project.config, &options.compiler)?;
+    // Keep only the test modules whose name contains the module filter.
+    if let Some(module) = &selection.module {
+        modules_abs_paths.retain(|path| {
+            get_module_name(&project.src_and_test_dirs, path)
+                .map_or(true, |name| name.contains(module.as_str()))
+        });
+        if modules_abs_paths.is_empty() {
+            log::error!("No test module name contains \"{module}\"");
+            return Ok(Generated::Done(Output::NoSelectedTests));
+        }
+    }
+
+    // Keep only the test modules affected by the changed files.
+    if let Some(changed) = &selection.changed {
+        let graph = ImportGraph::build(&project.src_and_test_dirs);
+        modules_abs_paths = graph
+            .affected(changed, &modules_abs_paths)
+            .into_iter()
+            .cloned()
+            .collect();
+        if modules_abs_paths.is_empty() {
+            log::error!("No test module is affected by the changes");
+            return Ok(Generated::Done(Output::NoSelectedTests));
+        }
+        log::error!(
+            "Running the tests of {} affected module(s)",
+            modules_abs_paths.len()
+        );
+    }
+
+    let elm_version = Project::elm_version(&project.config, || Ok(options.compiler_version))?;
+    let elm_version_str = elm_version.to_string();
 
     let tests_root = project
         .root_directory
@@ -143,7 +354,7 @@ pub fn main_helper(
         .context("Failed to convert to JSON the string generated for the tests elm.json")?;
     match std::fs::read_to_string(&tests_config_path) {
         Ok(old_conf) if tests_config_str == old_conf => (),
-        _ => std::fs::write(tests_config_path, tests_config_str)
+        _ => std::fs::write(tests_config_path, &tests_config_str)
             .context("Unable to write to generated elm.json")?,
     };
 
@@ -160,61 +371,256 @@ pub fn main_helper(
     // Find all potential tests
     log::info!("Finding all potential tests ...");
     let mut potential_tests = Vec::new();
+    let mut unexposed = Vec::new();
     for (module_name, path) in module_names.iter().zip(&modules_abs_paths) {
         let source =
             fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
-        for potential_test in crate::parser::potential_tests(&source) {
+        let found = crate::parser::potential_tests(&source);
+        // Diagnostics all start with the position in the file of what they are about.
+        let located = |slice: &str, message: String| {
+            let (line, column) = crate::parser::position(&source, slice);
+            format!("{}:{line}:{column}: {message}", path.display())
+        };
+        for name in found.unexposed {
+            unexposed.push(located(
+                name,
+                format!("{name} is a Test but is not exposed by {module_name}"),
+            ));
+        }
+        // With an explicit exposing list, all exposed values are already potential tests,
+        // so only the ones of a module exposing everything may be missed.
+        if let Some(unparsed) = found.unparsed.filter(|_| !found.explicit_exposing) {
+            log::error!(
+                "Warning: {}",
+                located(
+                    unparsed,
+                    format!("failed to parse {module_name} from here, tests defined after this point may be missed")
+                )
+            );
+        }
+        for (name, annotation) in found.other_types {
+            log::error!(
+                "Warning: {}",
+                located(
+                    name,
+                    format!("{name} is exposed by {module_name} but its type is {annotation} instead of Test")
+                )
+            );
+        }
+        let mut tests = found.tests;
+        if let Some(values) = selected_values.get(path) {
+            if let Some(missing) = values.iter().find(|v| !tests.contains(&v.as_str())) {
+                anyhow::bail!(
+                    "{missing} is not an exposed test of {module_name} in {}",
+                    path.display()
+                );
+            }
+            tests.retain(|t| values.contains(*t));
+        }
+        for potential_test in tests {
             potential_tests.push(format!("check {module_name}.{potential_test}"));
         }
     }
     potential_tests.sort();
+    unexposed.sort();
+    if options.strict && !unexposed.is_empty() {
+        anyhow::bail!(
+            "Some tests are not exposed, add them to the exposing list of their module:\n{}",
+            unexposed.join("\n")
+        );
+    }
+    for warning in &unexposed {
+        log::error!("Warning: {warning}");
+    }
 
     // Generate templated src/Runner.elm
-    let runner_template = include_template!("Runner.elm");
-    let runner_elm_file = tests_root.join("src").join("Runner.elm");
-    create_templated(
-        runner_template,  // template
-        &runner_elm_file, // output
+    let runner_elm = templated(
+        include_template!("Runner.elm"),
         &[
             ("{{ imports }}", &imports.join("\n")),
             ("{{ potential_tests }}", &potential_tests.join("\n    , ")),
         ],
-    )
-    .context(format!("Failed to write {}", runner_elm_file.display()))?;
+    );
 
-    // Compile the src/Runner.elm file into Runner.elm.js
-    let _preparation_time = start_time.elapsed().as_secs_f32();
-    log::info!("Spent {_preparation_time}s generating Runner.elm");
-    log::info!("Compiling the generated templated src/Runner.elm ...");
+    // Skip the compilation if Runner.elm.js was already compiled from the same inputs.
     let compiled_runner = tests_root.join("js").join("Runner.elm.js");
-    let command = compile(
-        elm_home,
-        &tests_root,       // current_dir
-        &options.compiler, // compiler
-        &compiled_runner,  // output
-        &options.report,   // report
-        [Path::new("src").join("Runner.elm")],
+    let runner_key_file = tests_root.join("js").join(RUNNER_KEY_FILE_NAME);
+    let runner_key = runner_inputs_key(
+        project,
+        &runner_elm,
+        &tests_config_str,
+        &options.compiler,
+        &options.compiler_version.to_string(),
+        &elm_version_str,
+        options.compile_mode,
     )?;
-    if command.status.success() {
-        log::warn!("✓ Compilation of tests modules succeeded");
-        Ok(Output::MakeSuccess {
+    let previous_key = fs::read_to_string(&runner_key_file).unwrap_or_default();
+    if previous_key.split_whitespace().next() == Some(&runner_key) && compiled_runner.exists() {
+        log::warn!("✓ Tests modules are unchanged since their last compilation");
+        return Ok(Generated::Done(Output::MakeSuccess {
             tests_root,
             modules_abs_paths,
             compiled_runner,
-        })
-    } else {
-        // Always put the json output of `elm make` to stdout to be consistent
-        // with the fact that the tests runner output also goes to stdout.
-        match options.report.as_str() {
-            "json" => std::io::stdout().write_all(&command.stderr),
-            _ => std::io::stderr().write_all(&command.stderr),
-        }?;
-        Ok(Output::MakeFailure)
+        }));
+    }
+    if runner_key_file.exists() {
+        fs::remove_file(&runner_key_file).context("Failed to remove the key of Runner.elm.js")?;
+    }
+    let runner_elm_file = tests_root.join("src").join("Runner.elm");
+    fs::write(&runner_elm_file, &runner_elm)
+        .context(format!("Failed to write {}", runner_elm_file.display()))?;
+    let _preparation_time = start_time.elapsed().as_secs_f32();
+    log::info!("Spent {_preparation_time}s generating Runner.elm");
+    cancel.check()?;
+    Ok(Generated::Runner(Runner {
+        tests_root,
+        modules_abs_paths,
+        compiled_runner,
+        key_file: runner_key_file,
+        key: runner_key,
+    }))
+}
+
+/// Write the errors captured from a failed compilation.
+pub fn report_failure(command: &std::process::Output, report: &str) -> std::io::Result<()> {
+    // Always put the json output of `elm make` to stdout to be consistent
+    // with the fact that the tests runner output also goes to stdout.
+    match report {
+        "json" => std::io::stdout().write_all(&command.stderr),
+        _ => std::io::stderr().write_all(&command.stderr),
     }
 }
 
+/// Find the test modules colocated with the code in the source directories.
+///
+/// A module is a test module if its file name ends with "Test.elm",
+/// or if it imports the Test module and exposes values annotated as `Test`.
+fn colocated_tests(project: &Project) -> Vec<PathBuf> {
+    let source_dirs = project
+        .src_and_test_dirs
+        .iter()
+        .filter(|dir| !project.tests_dirs.contains(dir));
+    let is_test_module = |path: &PathBuf| {
+        if path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .is_some_and(|s| s.ends_with("Test"))
+        {
+            return true;
+        }
+        let Ok(source) = fs::read_to_string(path) else {
+            log::debug!("Failed to read {}", path.display());
+            return false;
+        };
+        crate::parser::imports(&source).contains(&"Test")
+            && !crate::parser::potential_tests(&source)
+                .annotated_tests
+                .is_empty()
+    };
+    let tests: Vec<PathBuf> = source_dirs
+        .flat_map(test_files_within)
+        .filter(|path| is_test_module(path))
+        .collect();
+    log::info!("Found {} colocated test module(s)", tests.len());
+    tests
+}
+
+/// Key of all the inputs of the compilation of Runner.elm into Runner.elm.js:
+/// its source, the elm.json of the tests, the compiler and its version, its mode
+/// and all modules it may import.
+fn runner_inputs_key(
+    project: &Project,
+    runner_elm: &str,
+    tests_config: &str,
+    compiler: &str,
+    compiler_version: &str,
+    elm_version: &str,
+    mode: CompileMode,
+) -> anyhow::Result<String> {
+    let mut sources: Vec<(String, Vec<u8>)> = project
+        .src_and_test_dirs
+        .iter()
+        .flat_map(elm_files_within)
+        .map(|path| {
+            let content = fs::read(&path).context(format!("Failed to read {}", path.display()))?;
+            Ok((path.to_string_lossy().into_owned(), content))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    sources.sort();
+    let mode = format!("{mode:?}");
+    let inputs = [
+        std::env!("CARGO_PKG_VERSION"),
+        runner_elm,
+        tests_config,
+        compiler,
+        compiler_version,
+        elm_version,
+        &mode,
+    ];
+    let sources = sources
+        .iter()
+        .flat_map(|(path, content)| [path.as_bytes(), content]);
+    Ok(crate::utils::stable_key(
+        inputs.iter().map(|input| input.as_bytes()).chain(sources),
+    ))
+}
+
+/// Name of the files listing test files to skip, with the syntax of .gitignore files.
+const IGNORE_FILE_NAME: &str = ".elm-test-rs-ignore";
+
+/// List recursively all elm files within a directory of tests,
+/// except the ones ignored by the .gitignore or .elm-test-rs-ignore files within it.
+fn test_files_within<P: AsRef<Path>>(directory: P) -> impl Iterator<Item = PathBuf> {
+    let walker = ignore::WalkBuilder::new(directory)
+        .standard_filters(false)
+        .git_ignore(true)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .follow_links(true)
+        .build();
+    let entries = walker.filter_map(|e| e.ok());
+    entries.map(|e| e.into_path()).filter(|p| is_elm_file(p))
+}
+
+/// Absolute glob patterns of excluded test files,
+/// from --ignore globs relative to the project root,
+/// and negated patterns like "!tests/Helpers/**" relative to the current directory.
+fn excluded_patterns(
+    project_root: &Path,
+    ignore: &[String],
+    negated: &[String],
+) -> anyhow::Result<Vec<Pattern>> {
+    let current_dir = std::env::current_dir().context("Failed to get the current directory")?;
+    let absolute = |dir: &Path, glob: &str| {
+        if Path::new(glob).is_absolute() {
+            glob.to_string()
+        } else {
+            let dir = Pattern::escape(&dir.to_string_lossy());
+            format!("{dir}{}{glob}", std::path::MAIN_SEPARATOR)
+        }
+    };
+    let ignored = ignore.iter().map(|g| (g, absolute(project_root, g)));
+    let negated = negated.iter().map(|g| (g, absolute(&current_dir, &g[1..])));
+    ignored
+        .chain(negated)
+        .map(|(glob, pattern)| {
+            Pattern::new(&pattern).context(format!("Invalid glob pattern: {glob}"))
+        })
+        .collect()
+}
+
+/// Whether a path matches one of the exclusion patterns,
+/// where `*` does not match path separators, unlike `**`.
+fn is_excluded(patterns: &[Pattern], path: &Path) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    patterns.iter().any(|p| p.matches_path_with(path, options))
+}
+
 /// List recursively all elm files within a given directory.
-fn elm_files_within<P: AsRef<Path>>(directory: P) -> impl Iterator<Item = PathBuf> {
+pub fn elm_files_within<P: AsRef<Path>>(directory: P) -> impl Iterator<Item = PathBuf> {
     let walker = WalkDir::new(directory).follow_links(true);
     let entries = walker.into_iter().filter_map(|e| e.ok());
     entries.map(|e| e.into_path()).filter(|p| is_elm_file(p))
@@ -224,6 +630,36 @@ fn is_elm_file<P: AsRef<Path>>(p: P) -> bool {
     p.as_ref().extension() == Some(OsStr::new("elm"))
 }
 
+/// Top level values selected in test modules.
+type SelectedValues = HashMap<PathBuf, HashSet<String>>;
+
+/// Collect absolute paths of all elm files matching the arguments,
+/// as well as the top level values selected with arguments like "tests/ParserTest.elm::suite".
+/// Modules also given without a selected value are not in the returned map.
+fn get_selected_modules(args: &[String]) -> anyhow::Result<(HashSet<PathBuf>, SelectedValues)> {
+    let mut whole_modules = HashSet::new();
+    let mut selected_values = SelectedValues::new();
+    for arg in args {
+        let Some((pattern, value)) = arg.rsplit_once("::") else {
+            whole_modules.extend(get_elm_modules_abs_paths(std::slice::from_ref(arg))?);
+            continue;
+        };
+        let is_valid_value = value.starts_with(char::is_lowercase)
+            && value.chars().all(|c| c == '_' || c.is_alphanumeric());
+        if !is_valid_value {
+            anyhow::bail!("Invalid test value name \"{value}\" in {arg}");
+        }
+        for path in get_elm_modules_abs_paths(&[pattern.to_string()])? {
+            let values = selected_values.entry(path).or_default();
+            values.insert(value.to_string());
+        }
+    }
+    selected_values.retain(|path, _| !whole_modules.contains(path));
+    let mut paths = whole_modules;
+    paths.extend(selected_values.keys().cloned());
+    Ok((paths, selected_values))
+}
+
 /// Collect absolute paths of all elm files matching the patterns given as arguments.
 fn get_elm_modules_abs_paths(args: &[String]) -> anyhow::Result<HashSet<PathBuf>> {
     let mut glob_err = Ok(());
@@ -267,14 +703,19 @@ fn absolute_elm_path(path: &Path) -> anyhow::Result<PathBuf> {
     }
 }
 
-/// Compile an Elm module into a JS file (without --optimized)
+/// Compile an Elm module into a JS file, with the flag of the given compilation mode.
+/// The compiler errors are captured instead of being printed if `capture_stderr` is true.
+#[allow(clippy::too_many_arguments)]
 pub fn compile<P1, P2, I, S>(
     elm_home: &Path,
     current_dir: P1,
     compiler: &str,
     output: P2,
     report: &str,
+    mode: CompileMode,
     src: I,
+    capture_stderr: bool,
+    cancel: &Cancel,
 ) -> anyhow::Result<std::process::Output>
 where
     P1: AsRef<Path>,
@@ -306,24 +747,26 @@ If you installed elm locally with npm, maybe try running with npx such as:
         "json" => Some("--report=json"),
         _ => None,
     };
-    // Capture compiler output if --report=json.
-    let stderr = match report {
-        "json" => Stdio::piped(),
-        _ => Stdio::inherit(),
+    let stderr = if capture_stderr {
+        Stdio::piped()
+    } else {
+        Stdio::inherit()
     };
-    crate::utils::compiler_command(compiler, executable)
+    let child = crate::utils::compiler_command(compiler, executable)
         .env("ELM_HOME", elm_home)
         .arg("make")
         .arg(format!("--output={output}"))
         .args(report_arg)
+        .args(mode.flag())
         .args(src)
         .current_dir(current_dir)
         // stdio config, comment to see elm make output for debug
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(stderr)
-        .output()
-        .context(context_if_fails)
+        .spawn()
+        .context(context_if_fails)?;
+    crate::utils::wait_cancellable(child, cancel)
 }
 
 /// Replace the template keys and write result to output file.
@@ -332,40 +775,80 @@ pub fn create_templated<P: AsRef<Path>>(
     output: P,
     replacements: &[(&str, &str)],
 ) -> Result<(), std::io::Error> {
+    std::fs::write(output, templated(template, replacements))
+}
+
+/// Replace the template keys.
+fn templated(template: &str, replacements: &[(&str, &str)]) -> String {
     let mut output_str = template.to_string();
     replacements
         .iter()
         .for_each(|(from, to)| output_str = output_str.replacen(from, to, 1));
-    std::fs::write(output, output_str)
+    output_str
 }
 
-/// By finding the module name from the file path we can import it even if
-/// the file is full of errors. Elm will then report what’s wrong.
+/// Find the module name from the `module` declaration of the file,
+/// and check that it matches the file path within one of the source directories.
+/// With nested source directories, the longest matching one is preferred.
+///
+/// If the declaration cannot be parsed, the module name is found from the file path
+/// so we can import it even if the file is full of errors. Elm will then report what’s wrong.
 fn get_module_name(
     source_dirs: impl IntoIterator<Item = impl AsRef<Path>>,
     file: impl AsRef<Path>,
 ) -> anyhow::Result<String> {
     log::debug!("get_module_name of: {}", file.as_ref().display());
     let file = file.as_ref();
-    let matching_source_dir = {
-        let mut matching = source_dirs.into_iter().filter(|dir| file.starts_with(dir));
-        match (matching.next(), matching.next()) {
-            (None, None) => {
-                anyhow::bail!(
-                    "This file \"{}\" matches no source directory! Imports won’t work then.",
-                    file.display()
-                )
-            }
-            (Some(dir), None) => dir,
-            _ => anyhow::bail!("2+ matching source dirs for file \"{}\".", file.display()),
-        }
+    let mut matching: Vec<_> = source_dirs
+        .into_iter()
+        .filter(|dir| file.starts_with(dir))
+        .collect();
+    matching.sort_by_key(|dir| std::cmp::Reverse(dir.as_ref().components().count()));
+    let Some(longest_source_dir) = matching.first() else {
+        anyhow::bail!(
+            "This file \"{}\" matches no source directory! Imports won’t work then.",
+            file.display()
+        )
     };
 
+    let source = fs::read_to_string(file).unwrap_or_default();
+    let Some(declared) = crate::parser::module_name(&source) else {
+        return module_name_from_path(longest_source_dir.as_ref(), file);
+    };
+    let from_paths: Vec<_> = matching
+        .iter()
+        .map(|dir| module_name_from_path(dir.as_ref(), file))
+        .collect();
+    if from_paths
+        .iter()
+        .any(|name| name.as_deref().ok() == Some(declared))
+    {
+        return Ok(declared.to_string());
+    }
+    match &from_paths[0] {
+        Ok(from_path) => anyhow::bail!(
+            "The file {} declares the module {declared} but its path corresponds to the module {from_path}. Please rename the module or move the file to {}.",
+            file.display(),
+            longest_source_dir
+                .as_ref()
+                .join(declared.replace('.', "/"))
+                .with_extension("elm")
+                .display()
+        ),
+        Err(err) => anyhow::bail!(
+            "The file {} declares the module {declared} but its path does not match: {err}",
+            file.display()
+        ),
+    }
+}
+
+/// Module name of a file from its path within a source directory.
+fn module_name_from_path(source_dir: &Path, file: &Path) -> anyhow::Result<String> {
     let trimmed: PathBuf = file
-        .strip_prefix(&matching_source_dir)
+        .strip_prefix(source_dir)
         .context(format!(
             "Error while trimming prefix \"{}\" of \"{}\".",
-            matching_source_dir.as_ref().display(),
+            source_dir.display(),
             file.display()
         ))?
         .with_extension("");
@@ -392,8 +875,100 @@ fn get_module_name(
     Ok(module_name_parts.join("."))
 }
 
-fn is_valid_module_name(name: &str) -> bool {
+pub fn is_valid_module_name(name: &str) -> bool {
     !name.is_empty()
         && name.chars().next().unwrap().is_uppercase() // unwrap() is fine here
         && name.chars().all(|c| c == '_' || c.is_alphanumeric())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::TestDir;
+
+    #[test]
+    fn get_module_name() {
+        let test_dir = TestDir::new();
+        let dir = test_dir.path();
+        let src = dir.join("src");
+        let generated = src.join("generated");
+        let module = |path: &str, source: &str| test_dir.write(path, source);
+        let source_dirs = [&src, &generated];
+        let module_name = |file| super::get_module_name(source_dirs, file);
+
+        let file = module("src/Foo/Bar.elm", "module Foo.Bar exposing (..)");
+        assert_eq!(module_name(&file).unwrap(), "Foo.Bar");
+        // Nested source directories
+        let file = module("src/generated/Api.elm", "module Api exposing (..)");
+        assert_eq!(module_name(&file).unwrap(), "Api");
+        let file = module("src/generated/Api/Types.elm", "syntax error");
+        assert_eq!(module_name(&file).unwrap(), "Api.Types");
+        // Path and declaration do not match
+        let file = module("src/Foo/Baz.elm", "module Foo.Bar exposing (..)");
+        let err = module_name(&file).unwrap_err().to_string();
+        assert!(err.contains("declares the module Foo.Bar"), "{err}");
+        assert!(err.contains("corresponds to the module Foo.Baz"), "{err}");
+        assert!(module_name(&dir.join("Outside.elm")).is_err());
+    }
+
+    #[test]
+    fn selected_modules() {
+        let test_dir = TestDir::new();
+        let dir = test_dir.path();
+        let file = |name: &str| test_dir.write(name, "").display().to_string();
+        let (a, b) = (file("A.elm"), file("B.elm"));
+        let args = [
+            format!("{a}::stringSuite"),
+            format!("{a}::intSuite"),
+            format!("{b}::suite"),
+            b.clone(),
+        ];
+        let (paths, values) = super::get_selected_modules(&args).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(values.len(), 1);
+        let mut selected: Vec<&String> = values[&dir.join("A.elm")].iter().collect();
+        selected.sort();
+        assert_eq!(selected, ["intSuite", "stringSuite"]);
+        assert!(super::get_selected_modules(&[format!("{a}::Suite")]).is_err());
+    }
+
+    #[test]
+    fn excluded_test_files() {
+        let test_dir = TestDir::new();
+        let dir = test_dir.path();
+        let write = |path: &str, content: &str| test_dir.write(path, content);
+        for module in [
+            "Tests",
+            "Helpers/Util",
+            "Fixtures/Generated",
+            "Parser/LexerTest",
+        ] {
+            write(&format!("tests/{module}.elm"), "");
+        }
+        write("tests/.gitignore", "Generated.elm\n");
+        write(
+            "tests/Helpers/.elm-test-rs-ignore",
+            "*\n!.elm-test-rs-ignore\n",
+        );
+        let found = |ignore: &[&str], negated: &[&str]| {
+            let ignore: Vec<String> = ignore.iter().map(|s| s.to_string()).collect();
+            let negated: Vec<String> = negated.iter().map(|s| s.to_string()).collect();
+            let excluded = super::excluded_patterns(dir, &ignore, &negated).unwrap();
+            let mut found: Vec<String> = super::test_files_within(dir.join("tests"))
+                .filter(|path| !super::is_excluded(&excluded, path))
+                .map(|path| path.strip_prefix(dir).unwrap().display().to_string())
+                .collect();
+            found.sort();
+            found
+        };
+
+        assert_eq!(
+            found(&[], &[]),
+            ["tests/Parser/LexerTest.elm", "tests/Tests.elm"]
+        );
+        assert_eq!(found(&["tests/Parser/**"], &[]), ["tests/Tests.elm"]);
+        let negated = format!("!{}", dir.join("tests/*.elm").display());
+        assert_eq!(found(&[], &[&negated]), ["tests/Parser/LexerTest.elm"]);
+        let absolute = super::excluded_patterns(dir, &[], &["!/absolute/*.elm".into()]);
+        assert!(absolute.unwrap()[0].matches("/absolute/Foo.elm"));
+    }
+}
@@ -8,34 +8,187 @@ enum Exposing<'a> {
     Many(Vec<&'a str>),
 }
 
+#[derive(Debug, Default, PartialEq)]
+/// Potential tests of a module, found with their type annotations.
+pub struct PotentialTests<'a> {
+    /// Exposed values annotated as `Test`, or without annotation.
+    pub tests: Vec<&'a str>,
+    /// The potential tests explicitly annotated as `Test`.
+    pub annotated_tests: Vec<&'a str>,
+    /// Explicitly exposed values annotated with another type, and that type.
+    pub other_types: Vec<(&'a str, String)>,
+    /// Values annotated as `Test` missing from the explicit exposing list,
+    /// and not used by other definitions, like a test grouped in an exposed suite.
+    /// They are slices of the source, so their position can be found with `position`.
+    pub unexposed: Vec<&'a str>,
+    /// Rest of the source that could not be parsed, if any.
+    /// Potential tests defined there are missed, unless the exposing list is explicit.
+    pub unparsed: Option<&'a str>,
+    /// Whether the module lists its exposed values, instead of exposing all of them.
+    pub explicit_exposing: bool,
+}
+
 /// Returns potential tests in the module.
-/// Warning: does not support effect modules and definition of operators.
-pub fn potential_tests(src: &str) -> Vec<&str> {
-    alt((parse_file, parse_content))(src)
+pub fn potential_tests(src: &str) -> PotentialTests<'_> {
+    let (code, _) = ignore_not_code(src).unwrap_or((src, ()));
+    let has_header = peek(pair(
+        alt((tag("module"), tag("port"), tag("effect"))),
+        space_or_comment,
+    ))(code)
+    .is_ok();
+    let parsed = if has_header {
+        parse_file(code)
+    } else {
+        map(parse_content, all_potential_tests)(code)
+    };
+    match parsed {
+        Ok((rest, mut potential_tests)) => {
+            if !rest.is_empty() {
+                potential_tests.unparsed = Some(rest);
+            }
+            potential_tests
+        }
+        Err(_) => PotentialTests {
+            unparsed: Some(code),
+            ..Default::default()
+        },
+    }
+}
+
+/// A top level definition.
+struct Definition<'a> {
+    name: &'a str,
+    /// Normalized type annotation, if any.
+    annotation: Option<String>,
+    /// Everything after the equals sign.
+    body: &'a str,
+}
+
+impl Definition<'_> {
+    fn is_annotated_test(&self) -> bool {
+        self.annotation.as_deref().is_some_and(is_test_type)
+    }
+
+    /// Whether the body of the definition refers to the given identifier.
+    fn uses(&self, identifier: &str) -> bool {
+        self.body
+            .split(|c: char| !is_allowed_in_identifier(c))
+            .any(|word| word == identifier)
+    }
+}
+
+/// Potential tests of a module exposing all its values.
+fn all_potential_tests(definitions: Vec<Definition<'_>>) -> PotentialTests<'_> {
+    let tests: Vec<Definition> = definitions
+        .into_iter()
+        .filter(|def| is_potential_test(def.name))
+        .filter(|def| def.annotation.is_none() || def.is_annotated_test())
+        .collect();
+    PotentialTests {
+        annotated_tests: tests
+            .iter()
+            .filter(|def| def.is_annotated_test())
+            .map(|def| def.name)
+            .collect(),
+        tests: tests.iter().map(|def| def.name).collect(),
+        ..Default::default()
+    }
+}
+
+/// Line and column, starting at 1, of a slice of the source.
+pub fn position(src: &str, slice: &str) -> (usize, usize) {
+    let offset = slice.as_ptr() as usize - src.as_ptr() as usize;
+    let before = &src[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Whether a type annotation is `Test`, potentially qualified like `Test.Test`.
+fn is_test_type(annotation: &str) -> bool {
+    match annotation.rsplit_once('.') {
+        None => annotation == "Test",
+        Some((module, name)) => {
+            name == "Test" && module.chars().all(is_allowed_in_module_identifier)
+        }
+    }
+}
+
+/// Returns the name of the module, from its module declaration.
+pub fn module_name(src: &str) -> Option<&str> {
+    preceded(ignore_not_code, module_name_declaration)(src)
+        .ok()
         .map(|x| x.1)
-        .unwrap_or_default()
+        .filter(|name| name.starts_with(char::is_uppercase))
+}
+
+/// Returns the names of the modules imported by the module.
+pub fn imports(src: &str) -> Vec<&str> {
+    let module_header = opt(preceded(ignore_not_code, module_declaration));
+    let import = terminated(parse_import_name, ignore_not_code);
+    preceded(
+        pair(module_header, ignore_not_code),
+        fold_many0(import, Vec::new, |mut acc, name| {
+            acc.push(name);
+            acc
+        }),
+    )(src)
+    .map(|x| x.1)
+    .unwrap_or_default()
 }
 
-fn parse_file(input: &str) -> IResult<&str, Vec<&str>> {
+fn parse_file(input: &str) -> IResult<&str, PotentialTests<'_>> {
     // Parse the module declaration
     let (input, exposing) = preceded(ignore_not_code, module_declaration)(input)?;
-    if let Exposing::Many(exposed) = exposing {
-        return Ok((input, exposed));
-    }
 
     // Parse the rest of the file
-    parse_content(input)
+    let (input, definitions) = parse_content(input)?;
+    let Exposing::Many(exposed) = exposing else {
+        return Ok((input, all_potential_tests(definitions)));
+    };
+
+    // Exposed values without a definition found are kept as potential tests
+    let mut potential_tests = PotentialTests {
+        explicit_exposing: true,
+        ..Default::default()
+    };
+    for def in &definitions {
+        let is_used = || {
+            let mut others = definitions.iter().filter(|other| other.name != def.name);
+            others.any(|other| other.uses(def.name))
+        };
+        if def.is_annotated_test() && !exposed.contains(&def.name) && !is_used() {
+            potential_tests.unexposed.push(def.name);
+        }
+    }
+    for name in exposed {
+        let annotation = definitions
+            .iter()
+            .find(|def| def.name == name)
+            .and_then(|def| def.annotation.clone());
+        match annotation {
+            Some(annotation) if !is_test_type(&annotation) => {
+                potential_tests.other_types.push((name, annotation))
+            }
+            Some(_) => {
+                potential_tests.tests.push(name);
+                potential_tests.annotated_tests.push(name);
+            }
+            None => potential_tests.tests.push(name),
+        }
+    }
+    Ok((input, potential_tests))
 }
 
 fn module_declaration(input: &str) -> IResult<&str, Exposing<'_>> {
-    // port can be a keyword
-    let (input, _) = alt((tag("port"), success("")))(input)?;
+    let (input, _) = module_name_declaration(input)?;
 
-    // module keyword potentially surrounded by garbage
-    let (input, _) = delimited(ignore_not_code, tag("module"), ignore_not_code)(input)?;
-
-    // identifier of the module
-    let (input, _) = take_while(is_allowed_in_module_identifier)(input)?;
+    // where clause of effect modules, such as: where { command = MyCmd }
+    let where_clause = pair(
+        terminated(tag("where"), ignore_not_code),
+        delimited(char('{'), take_until("}"), char('}')),
+    );
+    let (input, _) = opt(preceded(ignore_not_code, where_clause))(input)?;
 
     // exposing keyword potentially surrounded by garbage
     let (input, _) = delimited(ignore_not_code, tag("exposing"), ignore_not_code)(input)?;
@@ -47,6 +200,17 @@ fn module_declaration(input: &str) -> IResult<&str, Exposing<'_>> {
     ))(input)
 }
 
+fn module_name_declaration(input: &str) -> IResult<&str, &str> {
+    // port or effect can be a keyword
+    let (input, _) = alt((tag("port"), tag("effect"), success("")))(input)?;
+
+    // module keyword potentially surrounded by garbage
+    let (input, _) = delimited(ignore_not_code, tag("module"), ignore_not_code)(input)?;
+
+    // identifier of the module
+    take_while1(is_allowed_in_module_identifier)(input)
+}
+
 // ------------------
 // Parsing the exposing
 
@@ -65,7 +229,7 @@ fn is_potential_test(identifier: &str) -> bool {
 }
 
 fn take_exposed_identifier(input: &str) -> IResult<&str, &str> {
-    let (input, identifier) = take_identifier(input)?;
+    let (input, identifier) = alt((take_identifier, take_operator))(input)?;
     let (input, _) = ignore_not_code(input)?;
     let (input, _) = alt((double_dot_expose, success("")))(input)?;
     Ok((input, identifier))
@@ -82,7 +246,7 @@ fn double_dot_expose(input: &str) -> IResult<&str, &str> {
 // ------------------
 // Parsing the content
 
-fn parse_content(input: &str) -> IResult<&str, Vec<&str>> {
+fn parse_content(input: &str) -> IResult<&str, Vec<Definition<'_>>> {
     // Parse and ignore all imports
     let (input, _) = ignore_not_code(input)?;
     let ignore_import = terminated(parse_import, ignore_not_code);
@@ -91,14 +255,36 @@ fn parse_content(input: &str) -> IResult<&str, Vec<&str>> {
     // Parse definitions
     let parse_declaration = alt((
         map(parse_type, |_| None),
-        map(parse_port, |_| None),
-        map(preceded(parse_header, parse_definition), Some),
-        map(parse_definition, Some),
+        map(parse_port, |(name, annotation)| {
+            Some(Definition {
+                name,
+                annotation: Some(annotation),
+                body: "",
+            })
+        }),
+        map(parse_infix, |_| None),
+        map(
+            pair(parse_header, parse_definition),
+            |((name, annotation), (_, body))| {
+                Some(Definition {
+                    name,
+                    annotation: Some(annotation),
+                    body,
+                })
+            },
+        ),
+        map(parse_definition, |(name, body)| {
+            Some(Definition {
+                name,
+                annotation: None,
+                body,
+            })
+        }),
     ));
     fold_many0(
         terminated(parse_declaration, ignore_not_code),
         Vec::new,
-        |mut acc: Vec<&str>, item| {
+        |mut acc: Vec<Definition>, item| {
             if let Some(decl) = item {
                 acc.push(decl);
             }
@@ -108,62 +294,102 @@ fn parse_content(input: &str) -> IResult<&str, Vec<&str>> {
 }
 
 fn parse_import(input: &str) -> IResult<&str, ()> {
-    let (input, _) = terminated(tag("import"), ignore_not_code)(input)?;
+    let (input, _) = terminated(tag("import"), many1_count(space_or_comment))(input)?;
     let (input, _) = take_body(input)?;
     Ok((input, ()))
 }
 
+fn parse_import_name(input: &str) -> IResult<&str, &str> {
+    let (input, _) = terminated(tag("import"), many1_count(space_or_comment))(input)?;
+    let (input, name) = take_while1(is_allowed_in_module_identifier)(input)?;
+    let (input, _) = take_body(input)?;
+    Ok((input, name))
+}
+
 fn parse_type(input: &str) -> IResult<&str, ()> {
-    let (input, _) = terminated(tag("type"), ignore_not_code)(input)?;
+    let (input, _) = terminated(tag("type"), many1_count(space_or_comment))(input)?;
     let (input, _) = take_body(input)?;
     Ok((input, ()))
 }
 
-fn parse_port(input: &str) -> IResult<&str, &str> {
+/// Port declaration, such as: port toJs : Value -> Cmd msg
+/// Its signature is its type annotation, so ports are never potential tests.
+fn parse_port(input: &str) -> IResult<&str, (&str, String)> {
     let (input, _) = tag("port")(input)?;
     let (input, _) = space_or_comment(input)?;
 
     let (input, _) = ignore_not_code(input)?;
 
-    // identifier of the variable
-    let (input, identifier) = take_identifier(input)?;
+    // identifier and type of the port
+    parse_header(input)
+}
 
-    // take everying until a new line followed by a char that is not a whitespace
+/// Infix declaration, such as: infix right 0 (<|) = apL
+fn parse_infix(input: &str) -> IResult<&str, ()> {
+    let (input, _) = terminated(tag("infix"), many1_count(space_or_comment))(input)?;
     let (input, _) = take_body(input)?;
-
-    Ok((input, identifier))
+    Ok((input, ()))
 }
 
-fn parse_header(input: &str) -> IResult<&str, &str> {
+fn parse_header(input: &str) -> IResult<&str, (&str, String)> {
     // identifier of the declaration, potentially followed by garbage
-    let (input, identifier) = terminated(take_identifier, ignore_not_code)(input)?;
+    let (input, identifier) = terminated(take_definition_name, ignore_not_code)(input)?;
 
     // : between identifier and type
     let (input, _) = terminated(tag(":"), ignore_not_code)(input)?;
 
     // type
-    let (input, _) = terminated(take_body, ignore_not_code)(input)?;
+    let (input, annotation) = terminated(recognize(take_body), ignore_not_code)(input)?;
 
-    Ok((input, identifier))
+    Ok((input, (identifier, normalize_type(annotation))))
+}
+
+/// Remove comments and extra whitespace from a type annotation.
+fn normalize_type(annotation: &str) -> String {
+    let token = alt((
+        map(space_or_comment, |_| " "),
+        take_till1(|c: char| c.is_whitespace() || c == '-' || c == '{'),
+        take(1usize),
+    ));
+    let code = fold_many0(token, String::new, |mut acc, token| {
+        acc.push_str(token);
+        acc
+    })(annotation)
+    .map(|x| x.1)
+    .unwrap_or_default();
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-fn parse_definition(input: &str) -> IResult<&str, &str> {
+fn parse_definition(input: &str) -> IResult<&str, (&str, &str)> {
     // identifier of the variable
-    let (input, identifier) = terminated(take_identifier, ignore_not_code)(input)?;
+    let (input, identifier) = terminated(take_definition_name, ignore_not_code)(input)?;
 
     // all the things between identifier and equals sign
     let (input, _) = terminated(take_until("="), tag("="))(input)?;
 
     // take everying until a new line followed by a char that is not a whitespace
-    let (input, _) = take_body(input)?;
+    let (input, body) = recognize(take_body)(input)?;
 
-    Ok((input, identifier))
+    Ok((input, (identifier, body)))
 }
 
 fn take_identifier(input: &str) -> IResult<&str, &str> {
     take_while1(is_allowed_in_identifier)(input)
 }
 
+/// Operator within parentheses, such as (|>)
+fn take_operator(input: &str) -> IResult<&str, &str> {
+    recognize(delimited(
+        tag("("),
+        take_while1(is_allowed_in_operator),
+        tag(")"),
+    ))(input)
+}
+
+fn take_definition_name(input: &str) -> IResult<&str, &str> {
+    alt((take_identifier, take_operator))(input)
+}
+
 // Things to ignore
 
 fn ignore_not_code(input: &str) -> IResult<&str, ()> {
@@ -227,6 +453,10 @@ fn is_allowed_in_module_identifier(c: char) -> bool {
     c.is_alphanumeric() || c == '_' || c == '.'
 }
 
+fn is_allowed_in_operator(c: char) -> bool {
+    "+-/*=.<>:&|^?%!".contains(c)
+}
+
 // ------------------
 // Parse strings
 
@@ -349,12 +579,37 @@ module{--}Main {-
 "#,
             Some(vec!["one", "two", "three"]),
         );
+        helper(
+            "effect module Task where { command = MyCmd } exposing (Task, perform, (|>))",
+            Some(vec!["perform"]),
+        );
+        helper(
+            "effect module Sub where {-{}-} { subscription = MySub } exposing (..)",
+            None,
+        );
+    }
+    #[test]
+    fn get_module_name() {
+        assert_eq!(
+            super::module_name("module Main exposing (..)"),
+            Some("Main")
+        );
+        assert_eq!(
+            super::module_name("{- doc -}\nport module{--}Main.Pain exposing (main)"),
+            Some("Main.Pain")
+        );
+        assert_eq!(
+            super::module_name("effect module Task where { command = MyCmd } exposing (..)"),
+            Some("Task")
+        );
+        assert_eq!(super::module_name("main = text \"\""), None);
+        assert_eq!(super::module_name("module exposing (..)"), None);
     }
     #[test]
     fn get_all_top_level_values() {
         let helper = |source: &str, expected: Vec<&str>| {
             let content = super::potential_tests(source);
-            assert_eq!(content, expected);
+            assert_eq!(content.tests, expected);
         };
 
         helper("type Test = Thi", vec![]);
@@ -413,6 +668,204 @@ five = something
             vec!["one", "two", "three", "four", "five"],
         );
     }
+    #[test]
+    fn filter_by_type_annotation() {
+        let source = "module Main exposing (..)
+import Test exposing (Test)
+import Test as T
+
+unannotated = test
+annotated : Test
+annotated = test
+qualified : T.Test
+qualified = test
+commented : {- the -} Test -- test
+commented = test
+helper : Int
+helper = 3
+function : String -> Test
+function name = test name
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(
+            potential_tests.tests,
+            vec!["unannotated", "annotated", "qualified", "commented"]
+        );
+        assert_eq!(
+            potential_tests.annotated_tests,
+            vec!["annotated", "qualified", "commented"]
+        );
+        let source = "module Main exposing (annotated, helper, function, undefined)
+import Test exposing (Test)
+
+annotated : Test
+annotated = test
+helper : Int
+helper = 3
+function :
+    String
+    -> Test
+function name = test name
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(potential_tests.tests, vec!["annotated", "undefined"]);
+        assert_eq!(potential_tests.annotated_tests, vec!["annotated"]);
+        assert!(potential_tests.unexposed.is_empty());
+        assert_eq!(
+            potential_tests.other_types,
+            vec![
+                ("helper", "Int".to_string()),
+                ("function", "String -> Test".to_string())
+            ]
+        );
+        let source = "port module Main exposing (toJs, fromJs, suite)
+import Test exposing (Test)
+
+port toJs : String -> Cmd msg
+
+port fromJs :
+    (String -> msg)
+    -> Sub msg
+
+suite : Test
+suite = test
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(potential_tests.tests, vec!["suite"]);
+        assert_eq!(
+            potential_tests.other_types,
+            vec![
+                ("toJs", "String -> Cmd msg".to_string()),
+                ("fromJs", "(String -> msg) -> Sub msg".to_string())
+            ]
+        );
+        assert_eq!(potential_tests.unparsed, None);
+    }
+    #[test]
+    fn operators_and_parse_failures() {
+        let source = "module Basics exposing ((|>), apR, suite)
+
+infix left 0 (|>) = apR
+
+(<?>) : a -> a -> a
+(<?>) a b = a
+
+apR : a -> (a -> b) -> b
+apR x f = f x
+
+suite : Test
+suite = test
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(potential_tests.tests, vec!["suite"]);
+        assert_eq!(potential_tests.unparsed, None);
+        let source = "module Main exposing (..)
+
+first = test
+
+( a, b ) = ( 1, 2 )
+
+second = test
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(potential_tests.tests, vec!["first"]);
+        let unparsed = potential_tests.unparsed.unwrap();
+        assert_eq!(super::position(source, unparsed), (5, 1));
+        assert!(!potential_tests.explicit_exposing);
+        let source = "module Main exposing (first, second)
+
+first = test
+
+( a, b ) = ( 1, 2 )
+
+second = test
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(potential_tests.tests, vec!["first", "second"]);
+        assert!(potential_tests.unparsed.is_some());
+        assert!(potential_tests.explicit_exposing);
+        let source = "module Main exposing";
+        let unparsed = super::potential_tests(source).unparsed;
+        assert_eq!(unparsed, Some(source));
+    }
+    #[test]
+    fn keyword_prefixed_names() {
+        let source = "module Main exposing (..)
+import0 = test
+type0 : Test
+type0 = test
+port0 = test
+infix0 = test
+";
+        let potential_tests = super::potential_tests(source);
+        assert_eq!(
+            potential_tests.tests,
+            vec!["import0", "type0", "port0", "infix0"]
+        );
+    }
+    #[test]
+    fn find_unexposed_tests() {
+        let source = "module Main exposing (exposed)
+import Test exposing (Test)
+
+exposed : Test
+exposed = test
+
+suite : Test
+suite =
+    describe \"suite\" [ grouped ]
+
+grouped : Test
+grouped = test
+";
+        let unexposed = super::potential_tests(source).unexposed;
+        assert_eq!(unexposed, vec!["suite"]);
+        assert_eq!(super::position(source, unexposed[0]), (7, 1));
+        let source = "module Main exposing (..)
+suite : Test
+suite = test
+";
+        assert!(super::potential_tests(source).unexposed.is_empty());
+    }
+    #[test]
+    fn get_imports() {
+        let helper = |source: &str, expected: Vec<&str>| {
+            assert_eq!(super::imports(source), expected);
+        };
+
+        helper("", vec![]);
+        helper("module Main exposing (..)", vec![]);
+        helper(
+            "module Main exposing (..)
+import Expect
+import Test exposing (Test, test)
+import Json.Decode as Decode
+",
+            vec!["Expect", "Test", "Json.Decode"],
+        );
+        helper(
+            "import Expect
+-- import Commented
+{- import Commented -}
+import
+    Multi.Line
+        exposing
+            ( a
+            , b
+            )
+",
+            vec!["Expect", "Multi.Line"],
+        );
+        helper(
+            "module Main exposing (..)
+import Expect
+
+importantTest = 3
+import NotAnImport
+",
+            vec!["Expect"],
+        );
+    }
 }
 
 #[cfg(test)]
@@ -480,3 +933,227 @@ mod nom_tests {
         asrt_eq("{- ✔ -}", Ok(("", " ✔ ")));
     }
 }
+
+#[cfg(test)]
+mod corpus_tests {
+    use std::path::Path;
+
+    /// Every Elm file of the corpus is fully parsed,
+    /// and its potential tests followed by its other exposed annotated values
+    /// are the ones listed in the .expected file next to it.
+    /// Run the test with UPDATE_EXPECTED=1 to write the .expected files.
+    #[test]
+    fn corpus() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/parser-corpus");
+        let mut count = 0;
+        for file in crate::make::elm_files_within(&corpus) {
+            let source = std::fs::read_to_string(&file).unwrap();
+            let potential_tests = super::potential_tests(&source);
+            assert_eq!(potential_tests.unparsed, None, "{}", file.display());
+            let found: String = (potential_tests.tests.iter().map(|t| format!("{t}\n")))
+                .chain(
+                    (potential_tests.other_types.iter())
+                        .map(|(name, annotation)| format!("{name} : {annotation}\n")),
+                )
+                .collect();
+            let expected_file = file.with_extension("expected");
+            if std::env::var_os("UPDATE_EXPECTED").is_some() {
+                std::fs::write(&expected_file, &found).unwrap();
+            }
+            let expected = std::fs::read_to_string(&expected_file).unwrap();
+            assert!(
+                !expected.trim().is_empty(),
+                "Nothing exposed in {}",
+                expected_file.display()
+            );
+            assert_eq!(found, expected, "{}", file.display());
+            count += 1;
+        }
+        assert!(count > 0, "No file in {}", corpus.display());
+    }
+
+    /// Elm files of the example projects are fully parsed.
+    #[test]
+    fn example_projects() {
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/example-projects");
+        for file in crate::make::elm_files_within(&examples) {
+            if file.components().any(|c| c.as_os_str() == "elm-stuff") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&file).unwrap();
+            let unparsed = super::potential_tests(&source).unparsed;
+            assert_eq!(unparsed, None, "{}", file.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use proptest::prelude::*;
+
+    /// Module declarations, followed by the exposing list.
+    const HEADERS: [&str; 3] = [
+        "module Main exposing",
+        "port module Main.Ports exposing",
+        "effect module Task where { command = MyCmd } exposing",
+    ];
+
+    /// Type annotations and their normalized form. The first five are tests.
+    const ANNOTATIONS: [(&str, &str); 8] = [
+        ("Test", "Test"),
+        ("Test.Test", "Test.Test"),
+        ("{- a {- nested -} comment -} Test", "Test"),
+        ("Test -- a comment", "Test"),
+        ("\n    Test", "Test"),
+        ("Int", "Int"),
+        ("String\n    -> Test", "String -> Test"),
+        ("List {- tests -} Test", "List Test"),
+    ];
+
+    /// Bodies of definitions, after the equals sign.
+    /// Generated names all contain a digit so bodies never refer to them.
+    const BODIES: [&str; 8] = [
+        " test",
+        "\n    Test.test \"name\" <|\n        \\() -> Expect.pass",
+        " \"\"\"\nfake = 1\n\"\"\"",
+        " '\"' {-\nfake = 2\n-}",
+        "\n    let\n        inner = 3\n    in\n    inner",
+        " 1 -- fake = 3",
+        " \"a {- b\" ++ \"-}\"",
+        "\n    {- {- nested -}\nfake = 4 -}\n    x",
+    ];
+
+    /// Code before each definition.
+    const SEPARATORS: [&str; 4] = [
+        "\n\n",
+        "\n\n\n-- a = 1\n",
+        "\n\n{-| Documentation\n    b = 2\n-}\n",
+        "\n\ntype Msg\n    = A\n    | B\n\n\ntype alias Model =\n    { a : Int }\n\n",
+    ];
+
+    #[derive(Debug, Clone)]
+    struct Definition {
+        name: String,
+        annotation: Option<usize>,
+        body: usize,
+        separator: usize,
+        exposed: bool,
+    }
+
+    impl Definition {
+        fn is_test(&self) -> bool {
+            self.annotation.map_or(true, |a| a < 5)
+        }
+    }
+
+    /// A generated Elm module, and what its potential tests should be.
+    #[derive(Debug, Clone)]
+    struct Module {
+        header: usize,
+        expose_all: bool,
+        definitions: Vec<Definition>,
+    }
+
+    impl Module {
+        fn source(&self) -> String {
+            let exposing = if self.expose_all {
+                "(..)".to_string()
+            } else {
+                let names = self.definitions.iter().filter(|d| d.exposed);
+                let mut items: Vec<&str> = names.map(|d| d.name.as_str()).collect();
+                items.extend(["Msg(..)", "Model", "(|>)"]);
+                format!("\n    ( {}\n    )", items.join("\n    , "))
+            };
+            let mut source = format!(
+                "{} {exposing}\n\nimport Expect\nimport Test exposing (Test)\n",
+                HEADERS[self.header]
+            );
+            for def in &self.definitions {
+                source.push_str(SEPARATORS[def.separator]);
+                if let Some(annotation) = def.annotation {
+                    source.push_str(&format!("{} : {}\n", def.name, ANNOTATIONS[annotation].0));
+                }
+                source.push_str(&format!("{} ={}\n", def.name, BODIES[def.body]));
+            }
+            source
+        }
+
+        fn tests(&self) -> Vec<&str> {
+            let definitions = self.definitions.iter();
+            definitions
+                .filter(|d| (self.expose_all || d.exposed) && d.is_test())
+                .map(|d| d.name.as_str())
+                .collect()
+        }
+
+        fn other_types(&self) -> Vec<(&str, String)> {
+            let definitions = self.definitions.iter();
+            definitions
+                .filter(|d| !self.expose_all && d.exposed && !d.is_test())
+                .map(|d| (d.name.as_str(), ANNOTATIONS[d.annotation.unwrap()].1.into()))
+                .collect()
+        }
+
+        fn unexposed(&self) -> Vec<&str> {
+            let definitions = self.definitions.iter();
+            definitions
+                .filter(|d| !self.expose_all && !d.exposed)
+                .filter(|d| d.annotation.is_some() && d.is_test())
+                .map(|d| d.name.as_str())
+                .collect()
+        }
+    }
+
+    fn module() -> impl Strategy<Value = Module> {
+        let definition = (
+            "(import|type|port|infix)?[a-z][a-zA-Z_]{0,6}",
+            proptest::option::of(0..ANNOTATIONS.len()),
+            0..BODIES.len(),
+            0..SEPARATORS.len(),
+            any::<bool>(),
+        );
+        let definitions = proptest::collection::vec(definition, 0..8);
+        (0..HEADERS.len(), any::<bool>(), definitions).prop_map(
+            |(header, expose_all, definitions)| {
+                let definitions = definitions.into_iter().enumerate();
+                let definitions =
+                    definitions.map(|(i, (name, annotation, body, sep, exposed))| Definition {
+                        name: format!("{name}{i}"),
+                        annotation,
+                        body,
+                        separator: sep,
+                        exposed,
+                    });
+                Module {
+                    header,
+                    expose_all,
+                    definitions: definitions.collect(),
+                }
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn generated_modules(module in module()) {
+            let source = module.source();
+            let potential_tests = super::potential_tests(&source);
+            prop_assert_eq!(potential_tests.unparsed, None);
+            prop_assert_eq!(potential_tests.tests, module.tests());
+            prop_assert_eq!(potential_tests.other_types, module.other_types());
+            prop_assert_eq!(potential_tests.unexposed, module.unexposed());
+        }
+
+        #[test]
+        fn never_panics(
+            source in "(module |exposing |import |type |port |infix |[a-z]+|[A-Z][a-z]*|[ \n]+|[=:(),.|]|\\{-|-\\}|--|\"|'|\\\\)*"
+        ) {
+            let potential_tests = super::potential_tests(&source);
+            let slices = potential_tests.tests.iter().chain(&potential_tests.unexposed);
+            for slice in slices.chain(&potential_tests.unparsed) {
+                super::position(&source, slice);
+            }
+            super::imports(&source);
+        }
+    }
+}
@@ -1,19 +1,27 @@
 use anyhow::Context;
-use notify_debouncer_mini::new_debouncer;
+use glob::Pattern;
 use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
 use pubgrub::version::SemanticVersion as SemVer;
 use pubgrub_dependency_provider_elm::project_config::ProjectConfig;
 use std::collections::BTreeSet;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::keys::{read_commands, Command, RawMode};
+use crate::utils::{Cancel, Cancelled};
+
 #[derive(Debug)]
 pub struct Project {
     pub config: ProjectConfig,
     pub src_and_test_dirs: BTreeSet<PathBuf>,
     pub root_directory: PathBuf,
+    /// Directories of the test modules (absolute paths), even the ones that do not exist yet.
+    pub tests_dirs: Vec<PathBuf>,
 }
 
 impl Project {
@@ -22,14 +30,22 @@ impl Project {
     /// An application pins an exact version in its elm.json, so that is
     /// authoritative. A package only declares a version constraint, so we ask
     /// the compiler which version is actually installed.
-    pub fn elm_version(config: &ProjectConfig, compiler: &str) -> anyhow::Result<SemVer> {
+    pub fn elm_version(
+        config: &ProjectConfig,
+        compiler_version: impl FnOnce() -> anyhow::Result<SemVer>,
+    ) -> anyhow::Result<SemVer> {
         match config {
             ProjectConfig::Application(app_config) => Ok(app_config.elm_version),
-            ProjectConfig::Package(_) => crate::utils::elm_version_from_compiler(compiler),
+            ProjectConfig::Package(_) => compiler_version(),
         }
     }
 
-    pub fn from_dir<P: AsRef<Path>>(root_directory: P) -> anyhow::Result<Project> {
+    /// Read the project in `root_directory`,
+    /// with test modules in the given directories, relative to the project root.
+    pub fn from_dir<P: AsRef<Path>>(
+        root_directory: P,
+        tests_dirs: &[impl AsRef<Path>],
+    ) -> anyhow::Result<Project> {
         let root_directory = crate::utils::absolute_path(root_directory)?;
 
         // Read project elm.json
@@ -52,29 +68,84 @@ impl Project {
             .collect::<Result<_, _>>()
             .context("It seems source directories do not all exist")?;
 
-        // Add tests/ to the list of source directories if it exists.
-        let tests_dir = root_directory.join("tests");
-        if tests_dir.exists() {
-            src_and_test_dirs.insert(tests_dir);
+        // Add the tests directories to the list of source directories if they exist.
+        let tests_dirs: Vec<PathBuf> = tests_dirs
+            .iter()
+            .map(|dir| crate::utils::absolute_path(root_directory.join(dir)))
+            .collect::<Result<_, _>>()?;
+        for tests_dir in &tests_dirs {
+            if tests_dir.exists() {
+                src_and_test_dirs.insert(tests_dir.clone());
+            }
         }
 
         Ok(Project {
             config,
             src_and_test_dirs,
             root_directory,
+            tests_dirs,
         })
     }
 
-    pub fn watch(&mut self, call_back: impl Fn(&Self) -> anyhow::Result<()>) -> anyhow::Result<()> {
-        // Create a channel to receive the events.
+    /// Call `call_back` once, then every time a file of interest changes
+    /// (according to `filter`), or when the user types a command (see `COMMANDS_HELP`).
+    ///
+    /// A run still in progress when files change is cancelled through
+    /// the `Cancel` flag passed to `call_back`, which should then return a `Cancelled` error.
+    /// Other errors are reported, and the watch goes on.
+    pub fn watch(
+        &mut self,
+        filter: &WatchFilter,
+        mut call_back: impl FnMut(&Self, &Selection, &Cancel) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        // Create a channel to receive the file events and user inputs.
         let (tx, rx) = channel();
         // Create a debounced watcher, delivering batches of settled events.
         // See discussion here for the debouncing duration.
         // https://users.rust-lang.org/t/how-to-make-good-usage-of-the-notify-crate-for-responsive-events/55891
-        let mut debouncer =
-            new_debouncer(Duration::from_millis(100), tx).context("Failed to start watcher")?;
+        let files_tx = tx.clone();
+        let cancel = Cancel::default();
+        let files_cancel = cancel.clone();
+        let files_filter = filter.clone();
+        let mut debouncer = new_debouncer(
+            Duration::from_millis(100),
+            move |events: DebounceEventResult| {
+                // Newer changes make the current run obsolete.
+                if let Ok(events) = &events {
+                    if events.iter().any(|e| files_filter.is_of_interest(&e.path)) {
+                        files_cancel.cancel();
+                    }
+                }
+                let _ = files_tx.send(WatchEvent::Files(events));
+            },
+        )
+        .context("Failed to start watcher")?;
         let recursive = RecursiveMode::Recursive;
 
+        // Forward the commands typed by the user, with single keypresses if possible.
+        // Quitting also cancels the current run, which is then not reported.
+        let retry_tx = tx.clone();
+        let raw_mode = RawMode::enable();
+        let keypresses = raw_mode.is_enabled();
+        let quit_cancel = cancel.clone();
+        let quitting = Arc::new(AtomicBool::new(false));
+        let input_quitting = Arc::clone(&quitting);
+        std::thread::spawn(move || {
+            read_commands(keypresses, |command| {
+                if command == Command::Quit {
+                    input_quitting.store(true, Ordering::Relaxed);
+                    quit_cancel.cancel();
+                }
+                tx.send(WatchEvent::Input(command)).is_ok()
+            })
+        });
+        let help = if keypresses { KEYS_HELP } else { COMMANDS_HELP };
+        let report = |result: anyhow::Result<()>| {
+            if !quitting.load(Ordering::Relaxed) {
+                report_run(result);
+            }
+        };
+
         // Watch the elm.json and the content of source directories.
         let elm_json_path = self.root_directory.join("elm.json");
         debouncer
@@ -88,44 +159,121 @@ impl Project {
                 .context(format!("Failed to watch {}", path.display()))?;
         }
 
+        // Watch the directories containing the extra files.
+        for (path, mode) in filter.extra_dirs() {
+            if path.exists() {
+                debouncer
+                    .watcher()
+                    .watch(&path, mode)
+                    .context(format!("Failed to watch {}", path.display()))?;
+            } else {
+                log::warn!("Not watching {} since it does not exist", path.display());
+            }
+        }
+
+        // Filters chosen by the user, kept for all subsequent runs.
+        let mut filters = Selection::default();
+
         // Call the function to execute passed as argument.
-        call_back(self).context("Initial run in watch mode")?;
-
-        // We only process an event if it is of interest to us, meaning the path
-        // is an elm file or elm.json or a directory.
-        let is_of_interest = |p: &Path| {
-            p.extension() == Some(OsStr::new("elm")) // this is an elm file
-                || p.ends_with("elm.json") // elm.json changed
-                || p.is_dir() // a directory changed
-        };
+        report(call_back(self, &filters, &cancel).context("Initial run in watch mode"));
 
         // Enter the watch loop.
         loop {
-            // Each message is a debounced batch of events (or a set of errors).
+            // Each files message is a debounced batch of events (or a set of errors).
             let events = match rx.recv().context("Error watching files")? {
-                Ok(events) => events,
-                Err(errors) => {
+                WatchEvent::Files(Ok(events)) => events,
+                WatchEvent::Files(Err(errors)) => {
                     log::debug!("Watch errors: {errors:?}");
                     continue;
                 }
+                WatchEvent::Input(command) => {
+                    let mut only_failed = false;
+                    match command {
+                        Command::Rerun => log_banner("Running tests"),
+                        Command::All => {
+                            filters.pattern = None;
+                            filters.module = None;
+                            log_banner("Running all tests");
+                        }
+                        Command::Failed => {
+                            only_failed = true;
+                            log_banner("Running the tests that failed");
+                        }
+                        Command::Pattern(pattern) => {
+                            filters.pattern = pattern;
+                            log_banner(&format!("Test name filter: {:?}", filters.pattern));
+                        }
+                        Command::Module(module) => {
+                            filters.module = module;
+                            log_banner(&format!("Test module filter: {:?}", filters.module));
+                        }
+                        Command::Seed => {
+                            let seed = random_seed();
+                            filters.seed = Some(seed);
+                            log_banner(&format!("Running tests with the new seed {seed}"));
+                        }
+                        Command::Quit => return Ok(()),
+                        Command::Help => {
+                            log::error!("{help}");
+                            continue;
+                        }
+                        Command::Unknown(command) => {
+                            log::error!("Unknown command \"{command}\"\n{help}");
+                            continue;
+                        }
+                    }
+                    let selection = Selection {
+                        only_failed,
+                        ..filters.clone()
+                    };
+                    cancel.reset();
+                    report(call_back(self, &selection, &cancel));
+                    continue;
+                }
             };
             log::debug!("{events:?}");
 
-            // Find the first changed path that is of interest to us, if any.
-            let changed_path = events
-                .iter()
-                .map(|event| event.path.as_path())
-                .find(|p| is_of_interest(p));
-            let changed_path = match changed_path {
-                Some(p) => p.to_path_buf(),
-                None => continue,
-            };
+            // Find all changed paths that are of interest to us, if any.
+            let mut changed_paths: Vec<PathBuf> = events
+                .into_iter()
+                .map(|event| event.path)
+                .filter(|p| filter.is_of_interest(p))
+                .collect();
 
-            // drain event queue
-            for _ in rx.try_iter() {}
+            // Drain the file events queue, postponing the user inputs after this run.
+            // Changes arriving after that will cancel the run.
+            cancel.reset();
+            let mut postponed = Vec::new();
+            for event in rx.try_iter() {
+                match event {
+                    WatchEvent::Files(Ok(events)) => changed_paths.extend(
+                        events
+                            .into_iter()
+                            .map(|event| event.path)
+                            .filter(|p| filter.is_of_interest(p)),
+                    ),
+                    WatchEvent::Files(Err(_)) => {}
+                    input => postponed.push(input),
+                }
+            }
+            for input in postponed {
+                let _ = retry_tx.send(input);
+            }
+            changed_paths.sort();
+            changed_paths.dedup();
+            let Some(first_changed_path) = changed_paths.first().cloned() else {
+                continue;
+            };
 
-            // Load the potential updated elm.json.
-            let new_project = Project::from_dir(&self.root_directory)?;
+            // Load the potential updated elm.json, waiting for a fix if it is invalid.
+            let new_project = match Project::from_dir(&self.root_directory, &self.tests_dirs) {
+                Ok(project) => project,
+                Err(err) => {
+                    log_banner("Invalid project configuration");
+                    log::error!("{err:?}\n{WATCHING_MSG}");
+                    continue;
+                }
+            };
 
             // Update watched directories if they changed.
             let old_src_dirs = &self.src_and_test_dirs;
@@ -149,21 +297,209 @@ impl Project {
             *self = new_project;
 
             // Log to stderr that a change was detected.
-            let relative_path =
-                pathdiff::diff_paths(&changed_path, &self.root_directory).context(format!(
+            let relative_path = pathdiff::diff_paths(&first_changed_path, &self.root_directory)
+                .context(format!(
                     "Could not get path {} relative to path {}",
-                    changed_path.display(),
+                    first_changed_path.display(),
                     self.root_directory.display()
                 ))?;
-            let detection_msg = format!("Change detected in {}", relative_path.display());
-            log::error!(
-                "\n\n\n\n{}\n{}\n\n\n\n",
-                detection_msg,
-                "=".repeat(detection_msg.len())
-            );
+            let detection_msg = match changed_paths.len() {
+                1 => format!("Change detected in {}", relative_path.display()),
+                n => format!(
+                    "Change detected in {} and {} other files",
+                    relative_path.display(),
+                    n - 1
+                ),
+            };
+            log_banner(&detection_msg);
+
+            // Only Elm files changes can be narrowed down to affected tests.
+            let run_all = changed_paths
+                .iter()
+                .any(|p| p.extension() != Some(OsStr::new("elm")) || p.is_dir());
+            let selection = Selection {
+                changed: Some(changed_paths).filter(|_| !run_all),
+                ..filters.clone()
+            };
 
             // Call the function to execute passed as argument.
-            call_back(self).context("Subsequent run in watch mode")?;
+            report(call_back(self, &selection, &cancel));
         }
     }
 }
+
+#[derive(Debug, Clone)]
+/// Decides which changed files trigger a new run in watch mode.
+pub struct WatchFilter {
+    root_directory: PathBuf,
+    /// Additional files of interest, relative to the project root.
+    extra: Vec<Pattern>,
+    /// Files never of interest, relative to the project root.
+    ignore: Vec<Pattern>,
+}
+
+/// Directories never of interest, such as the one where tests are compiled.
+const ALWAYS_IGNORED: [&str; 3] = ["elm-stuff", "node_modules", ".git"];
+
+impl WatchFilter {
+    pub fn new(root_directory: &Path, extra: &[String], ignore: &[String]) -> anyhow::Result<Self> {
+        let patterns = |globs: &[String]| -> anyhow::Result<Vec<Pattern>> {
+            globs
+                .iter()
+                .map(|g| Pattern::new(g).context(format!("Invalid glob pattern: {g}")))
+                .collect()
+        };
+        Ok(WatchFilter {
+            root_directory: root_directory.to_path_buf(),
+            extra: patterns(extra)?,
+            ignore: patterns(ignore)?,
+        })
+    }
+
+    /// We only process an event if it is of interest to us, meaning the path
+    /// is not ignored, and is an elm file or elm.json or a directory or an extra file.
+    fn is_of_interest(&self, p: &Path) -> bool {
+        let relative = p.strip_prefix(&self.root_directory).unwrap_or(p);
+        let matches = |pattern: &Pattern| pattern.matches_path(relative) || pattern.matches_path(p);
+        if relative
+            .iter()
+            .any(|c| ALWAYS_IGNORED.iter().any(|i| c == *i))
+            || self.ignore.iter().any(matches)
+        {
+            return false;
+        }
+        p.extension() == Some(OsStr::new("elm")) // this is an elm file
+            || p.ends_with("elm.json") // elm.json changed
+            || p.is_dir() // a directory changed
+            || self.extra.iter().any(matches) // an extra file changed
+    }
+
+    /// Directories to watch for the extra files,
+    /// made of the components of each pattern preceding the first wildcard.
+    fn extra_dirs(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut dirs = Vec::new();
+        for pattern in &self.extra {
+            let pattern = Path::new(pattern.as_str());
+            let literal: PathBuf = pattern
+                .components()
+                .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+                .collect();
+            // Watch the parent directory of a single file, since editors may replace it.
+            let dir = match literal.parent() {
+                Some(parent) if literal == pattern => (
+                    self.root_directory.join(parent),
+                    RecursiveMode::NonRecursive,
+                ),
+                _ => (self.root_directory.join(literal), RecursiveMode::Recursive),
+            };
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+}
+
+/// Report the outcome of a run in watch mode.
+fn report_run(result: anyhow::Result<()>) {
+    match result {
+        Ok(()) => log::error!("{WATCHING_MSG}"),
+        Err(err) if err.is::<Cancelled>() => log::error!("\nRun cancelled by newer changes"),
+        Err(err) => log::error!("\nError: {err:?}\n{WATCHING_MSG}"),
+    }
+}
+
+const WATCHING_MSG: &str = "\nWatching for changes... Press Enter to rerun, or a command: a, f, p, m, s or q. Press h for help.";
+
+const KEYS_HELP: &str = "Watch mode keys:
+  Enter  rerun the tests
+  a      run all tests, removing the name and module filters
+  f      run only the tests that failed in the last run
+  p      type a pattern and Enter to only run tests whose description contains it (empty to remove it)
+  m      type a string and Enter to only run test modules whose name contains it (empty to remove it)
+  s      run the tests with a new random seed
+  h      show this help
+  q      quit (also Ctrl-C)";
+
+/// Commands are typed on a line when keypresses cannot be read one at a time,
+/// on Windows or when the input is not a terminal.
+const COMMANDS_HELP: &str = "Watch mode commands, followed by Enter
+(single keypresses are only supported in Unix terminals):
+  (empty)      rerun the tests
+  a            run all tests, removing the name and module filters
+  f            run only the tests that failed in the last run
+  p <pattern>  only run tests whose description contains the pattern (no pattern to remove it)
+  m <module>   only run test modules whose name contains the given string (no string to remove it)
+  s            run the tests with a new random seed
+  h            show this help
+  q            quit";
+
+/// Random seed from the current time, like the default --seed.
+fn random_seed() -> u32 {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+    now.map(|d| d.as_nanos() as u32).unwrap_or_default()
+}
+
+/// Log a message standing out from the output of the previous run.
+fn log_banner(msg: &str) {
+    log::error!("\n\n\n\n{}\n{}\n\n\n\n", msg, "=".repeat(msg.len()));
+}
+
+/// Event received in watch mode.
+enum WatchEvent {
+    /// A batch of file events from the watcher.
+    Files(DebounceEventResult),
+    /// A command typed by the user.
+    Input(Command),
+}
+
+#[derive(Debug, Default, Clone)]
+/// Tests to run, as decided in watch mode. The default is to run all tests.
+pub struct Selection {
+    /// Only run the test modules affected by these changed (or removed) Elm files.
+    pub changed: Option<Vec<PathBuf>>,
+    /// Only run the tests that failed in the previous run.
+    pub only_failed: bool,
+    /// Only run tests whose description contains this pattern, instead of --filter.
+    pub pattern: Option<String>,
+    /// Only run test modules whose name contains this string.
+    pub module: Option<String>,
+    /// Seed to use instead of --seed.
+    pub seed: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchFilter;
+    use notify_debouncer_mini::notify::RecursiveMode;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn watch_filter() {
+        let root = Path::new("/project");
+        let extra = [
+            "tests/fixtures/**/*.json".to_string(),
+            "data.txt".to_string(),
+        ];
+        let ignore = ["src/Generated/**".to_string()];
+        let filter = WatchFilter::new(root, &extra, &ignore).unwrap();
+        let of_interest = |path: &str| filter.is_of_interest(&root.join(path));
+        assert!(of_interest("src/Main.elm"));
+        assert!(of_interest("elm.json"));
+        assert!(of_interest("tests/fixtures/a/b.json"));
+        assert!(of_interest("data.txt"));
+        assert!(!of_interest("src/Generated/Api.elm"));
+        assert!(!of_interest("elm-stuff/tests-0.19.1/src/Runner.elm"));
+        assert!(!of_interest("tests/fixtures/b.txt"));
+        assert_eq!(
+            filter.extra_dirs(),
+            [
+                (
+                    PathBuf::from("/project/tests/fixtures"),
+                    RecursiveMode::Recursive
+                ),
+                (PathBuf::from("/project"), RecursiveMode::NonRecursive)
+            ]
+        );
+    }
+}
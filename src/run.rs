@@ -1,14 +1,18 @@
 //! Module dealing with actually running all the tests.
 
-use crate::make::Output;
-use crate::project::Project;
+use crate::make::{CompileMode, Generated, Output};
+use crate::project::{Project, Selection, WatchFilter};
+use crate::runtime::{Detected, Executables, Runtime};
+use crate::supervisor::{Spawner, Supervisor};
+use crate::utils::hash_key;
+use crate::utils::Cancel;
 use anyhow::Context;
+use pubgrub::version::SemanticVersion;
 use regex::Regex;
 use std::fs;
-use std::io::Write;
 use std::num::NonZeroU32;
-use std::path::Path;
-use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use crate::include_template;
 
@@ -20,16 +24,11 @@ pub struct Options {
     pub workers: u32,
     pub filter: Option<String>,
     pub reporter: String,
-    pub runtime: Runtime,
-}
-
-#[derive(Debug)]
-/// The runtime to be used.
-pub enum Runtime {
-    /// Node is the default runtime.
-    Node,
-    /// Deno is an alternative runtime.
-    Deno,
+    /// None means auto-detection of the runtime.
+    pub runtime: Option<Runtime>,
+    pub executables: Executables,
+    /// Maximum duration of a single test, None if unlimited.
+    pub timeout: Option<Duration>,
 }
 
 /// Wrapper for the main_helper function with "watch" functionality.
@@ -50,85 +49,250 @@ pub fn main(
     );
     log::warn!("\n{}\n{}\n", title, "-".repeat(title.len()));
 
-    let mut project = Project::from_dir(elm_project_root)?;
+    let runtime = crate::runtime::detect(run_options.runtime, &run_options.executables)?;
+    let mut project = Project::from_dir(elm_project_root, &make_options.tests_dirs)?;
+    let mut session = Session::new();
     if make_options.watch {
-        project.watch(|project| {
-            main_helper(elm_home, project, &make_options, &run_options).map(|_| ())
+        let filter = WatchFilter::new(
+            &project.root_directory,
+            &make_options.watch_extra,
+            &make_options.watch_ignore,
+        )?;
+        project.watch(&filter, |project, selection, cancel| {
+            main_helper(
+                elm_home,
+                project,
+                selection,
+                cancel,
+                &make_options,
+                &run_options,
+                &runtime,
+                &mut session,
+            )
+            .map(|_| ())
         })?;
         Ok(0)
     } else {
-        main_helper(elm_home, &project, &make_options, &run_options)
+        main_helper(
+            elm_home,
+            &project,
+            &Selection::default(),
+            &Cancel::default(),
+            &make_options,
+            &run_options,
+            &runtime,
+            &mut session,
+        )
+    }
+}
+
+/// State kept between runs in watch mode, to avoid redoing work when possible.
+pub struct Session {
+    /// The supervisor, keeping the reporter alive between runs.
+    supervisor: Supervisor,
+    /// Spawner and reporter key of the last run, to rerun its failed tests.
+    last_run: Option<(Box<dyn Spawner + Send>, u64)>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session {
+            supervisor: Supervisor::new(),
+            last_run: None,
+        }
     }
 }
 
 /// Main function, preparing and running the tests.
-/// It has multiple steps that can be summarized as:
-///
-///  1. Generate and compile `Runner.elm` with a master test concatenating all found exposed tests.
-///  2. Kernel-patch it and wrapp it into a Node worker module.
-///  3. Compile `Reporter.elm` into a Node module.
-///  4. Generate and start the Node supervisor program.
 ///
 /// Returns the the last exit code.
+#[allow(clippy::too_many_arguments)]
 fn main_helper(
     elm_home: &Path,
     project: &Project,
+    selection: &Selection,
+    cancel: &Cancel,
     make_options: &crate::make::Options,
     run_options: &Options,
+    runtime: &Detected,
+    session: &mut Session,
 ) -> anyhow::Result<i32> {
+    if selection.only_failed {
+        return rerun_failures(run_options, cancel, session);
+    }
+    let prepared = prepare(
+        elm_home,
+        project,
+        selection,
+        cancel,
+        make_options,
+        run_options,
+        runtime,
+    )?;
+    run_prepared(prepared, run_options, cancel, session)
+}
+
+/// Tests prepared to be run.
+pub enum Prepared {
+    /// There is nothing to run, and this is the exit code.
+    Done(i32),
+    /// The spawner of the runner and reporter programs, and the key of the reporter.
+    Ready(Box<dyn Spawner + Send>, u64),
+}
+
+/// Prepare the tests to be run.
+/// It has multiple steps that can be summarized as:
+///
+///  1. Generate `Runner.elm` with a master test concatenating all found exposed tests.
+///  2. Compile it, unless it was already compiled from the same inputs,
+///     and at the same time compile `Reporter.elm` into a module for the chosen runtime,
+///     unless it was already compiled from the same inputs (`Reporter.elm.js.key` file).
+///  3. Kernel-patch `Runner.elm.js`, unless it already is, and wrapp it into a worker module.
+///  4. Generate the glue code of runner and reporter programs for the chosen runtime.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare(
+    elm_home: &Path,
+    project: &Project,
+    selection: &Selection,
+    cancel: &Cancel,
+    make_options: &crate::make::Options,
+    run_options: &Options,
+    runtime: &Detected,
+) -> anyhow::Result<Prepared> {
     // let start_time = std::time::Instant::now();
 
-    // Compile the Runner.elm file.
-    let (tests_root, modules_abs_paths, compiled_runner) =
-        match crate::make::main_helper(elm_home, project, make_options)? {
-            Output::MakeFailure => return Ok(1),
-            Output::MakeSuccess {
-                tests_root,
-                modules_abs_paths,
-                compiled_runner,
-            } => (tests_root, modules_abs_paths, compiled_runner),
+    let es_module = match runtime.runtime {
+        Runtime::Node | Runtime::Embedded => false,
+        Runtime::Deno | Runtime::Bun => true,
+    };
+    let patch = if es_module { "es-module" } else { "commonjs" };
+
+    // Generate the Runner.elm file.
+    let generate = || crate::make::generate(elm_home, project, make_options, selection, cancel);
+    let mut generated = generate()?;
+    // An unchanged Runner.elm.js is compiled again if it was patched for another kind of module.
+    if let Generated::Done(Output::MakeSuccess {
+        compiled_runner, ..
+    }) = &generated
+    {
+        if applied_patch(compiled_runner).is_some_and(|applied| applied != patch) {
+            fs::remove_file(runner_key_path(compiled_runner))
+                .context("Failed to remove the key of Runner.elm.js")?;
+            generated = generate()?;
+        }
+    }
+    let tests_root = match &generated {
+        Generated::Done(Output::MakeFailure) => return Ok(Prepared::Done(1)),
+        Generated::Done(Output::NoSelectedTests) => return Ok(Prepared::Done(0)),
+        Generated::Done(Output::MakeSuccess { tests_root, .. }) => tests_root.clone(),
+        Generated::Runner(runner) => runner.tests_root.clone(),
+    };
+
+    // Compile the Reporter.elm into Reporter.elm.js,
+    // unless it was already compiled from the same inputs in a previous run.
+    // The elm.json of the tests records both the dependencies and the version of the compiler.
+    let reporter = Reporter::new(&tests_root, make_options, runtime.runtime)?;
+    let compile_reporter = !reporter.is_up_to_date();
+    if compile_reporter {
+        log::info!("Compiling Reporter.elm.js ...");
+        reporter.write_sources()?;
+    } else {
+        log::info!("Reporter.elm.js is up to date");
+    }
+
+    // Compile Runner.elm and Reporter.elm concurrently.
+    // The reporter has its own root directory, so each compiler holds the lock of a different elm-stuff.
+    // The errors of Reporter.elm are captured to be reported after the ones of Runner.elm.
+    let (output, reporter_command) = std::thread::scope(|scope| {
+        let reporter_thread = compile_reporter
+            .then(|| scope.spawn(|| reporter.compile(elm_home, make_options, cancel)));
+        let output = match generated {
+            Generated::Done(output) => Ok(output),
+            Generated::Runner(runner) => runner
+                .compile(elm_home, make_options, cancel)
+                .and_then(|command| runner.finish(&command, make_options)),
         };
+        let reporter_command = reporter_thread.map(|thread| {
+            thread
+                .join()
+                .expect("The Reporter.elm compilation thread panicked")
+        });
+        (output, reporter_command.transpose())
+    });
+    let (output, reporter_command) = (output?, reporter_command?);
+    if let Some(command) = &reporter_command {
+        if command.status.success() {
+            reporter.finish()?;
+        } else {
+            crate::make::report_failure(command, &make_options.report)?;
+            return Ok(Prepared::Done(1));
+        }
+    }
+    let compiled_reporter_key = reporter.key;
+    let (tests_root, modules_abs_paths, compiled_runner) = match output {
+        Output::MakeFailure => return Ok(Prepared::Done(1)),
+        Output::NoSelectedTests => return Ok(Prepared::Done(0)),
+        Output::MakeSuccess {
+            tests_root,
+            modules_abs_paths,
+            compiled_runner,
+        } => (tests_root, modules_abs_paths, compiled_runner),
+    };
 
     // Add a kernel patch to the generated code in order to be able to recognize
     // values of type Test at runtime with the `check: a -> Maybe Test` function.
-    log::info!("Kernel-patching Runner.elm.js ...");
-    let compiled_runner_src = fs::read_to_string(&compiled_runner).context(format!(
-        "Failed to read newly created file {}",
-        compiled_runner.display()
-    ))?;
-    let es_module = match run_options.runtime {
-        Runtime::Node => false,
-        Runtime::Deno => true,
-    };
-    fs::write(
-        &compiled_runner,
-        kernel_patch_tests(&compiled_runner_src, es_module).context(format!(
-            "Failed to patch the file {}",
+    if applied_patch(&compiled_runner).as_deref() == Some(patch) {
+        log::info!("Runner.elm.js is already kernel-patched");
+    } else {
+        log::info!("Kernel-patching Runner.elm.js ...");
+        let compiled_runner_src = fs::read_to_string(&compiled_runner).context(format!(
+            "Failed to read newly created file {}",
             compiled_runner.display()
-        ))?,
-    )
-    .context(format!(
-        "Failed to write the patched file {}",
-        compiled_runner.display()
-    ))?;
+        ))?;
+        let patched = kernel_patch_tests(&compiled_runner_src, es_module).with_context(|| {
+            format!(
+                "Failed to patch the file {}, compiled with elm-explorations/test {}.\n\
+                 Please report this bug: https://github.com/mpizenberg/elm-test-rs/issues/new",
+                compiled_runner.display(),
+                testlib_version(&tests_root)
+                    .as_deref()
+                    .unwrap_or("(unknown version)"),
+            )
+        })?;
+        fs::write(&compiled_runner, patched).context(format!(
+            "Failed to write the patched file {}",
+            compiled_runner.display()
+        ))?;
+        // Record the patch after the key of the inputs of Runner.elm.js
+        let key_path = runner_key_path(&compiled_runner);
+        let key =
+            fs::read_to_string(&key_path).context("Failed to read the key of Runner.elm.js")?;
+        fs::write(&key_path, format!("{} {patch}", key.trim()))
+            .context("Failed to write the key of Runner.elm.js")?;
+    }
 
     // Generate the node_runner.js node module embedding the Elm runner
 
-    let (runner_name, runner_template) = match run_options.runtime {
+    let (runner_name, runner_template) = match runtime.runtime {
         Runtime::Node => ("node_runner.js", include_template!("node_runner.js")),
         Runtime::Deno => ("deno_runner.mjs", include_template!("deno_runner.mjs")),
+        Runtime::Bun => ("bun_runner.mjs", include_template!("bun_runner.mjs")),
+        Runtime::Embedded => (
+            "embedded_runner.js",
+            include_template!("embedded_runner.js"),
+        ),
     };
     let polyfills = include_template!("node_polyfills.js");
     let runner_path = tests_root.join("js").join(runner_name);
-    let filter = match &run_options.filter {
-        None => "null".to_string(),
-        Some(s) => format!("\"{s}\""),
-    };
+    let seed = selection.seed.unwrap_or(run_options.seed).to_string();
+    // A JSON string (or null) is a valid JS literal, whatever characters the filter contains.
+    let filter = serde_json::to_string(&selection.pattern.as_ref().or(run_options.filter.as_ref()))
+        .context("Failed to convert the test name filter to JSON")?;
     crate::make::create_templated(
         runner_template, // template
         &runner_path,    // output
         &[
-            ("{{ initialSeed }}", &run_options.seed.to_string()),
+            ("{{ initialSeed }}", &seed),
             ("{{ fuzzRuns }}", &run_options.fuzz.to_string()),
             ("{{ filter }}", &filter),
             ("{{ polyfills }}", polyfills),
@@ -136,62 +300,36 @@ fn main_helper(
     )
     .context(format!("Failed to write {}", runner_path.display()))?;
 
-    // Compile the Reporter.elm into Reporter.elm.js
-    log::info!("Compiling Reporter.elm.js ...");
-    let reporter_template = include_template!("Reporter.elm");
-    let reporter_elm_path = tests_root.join("src").join("Reporter.elm");
-    std::fs::write(&reporter_elm_path, reporter_template)
-        .context("Error writing Reporter.elm to test folder")?;
-    let compiled_reporter = tests_root.join("js").join("Reporter.elm.js");
-    // let compile_time = std::time::Instant::now();
-    let command = crate::make::compile(
-        elm_home,
-        &tests_root,            // current_dir
-        &make_options.compiler, // compiler
-        &compiled_reporter,     // output
-        &make_options.report,   // report
-        [&reporter_elm_path],
-    )?;
-    if !command.status.success() {
-        return Ok(1);
-    }
-
-    // For a Deno runtime, convert the compiled Reporter.elm.js into an ES module.
-    if let Runtime::Deno = run_options.runtime {
-        let compiled_reporter_code = fs::read_to_string(&compiled_reporter)?;
-        fs::write(
-            &compiled_reporter,
-            into_es_module(&replace_console_log(&compiled_reporter_code)),
-        )?;
-    }
-
-    // Generate a package.json specifying that all JS files follow CommonJS.
+    // Generate a package.json specifying whether all JS files follow CommonJS
+    // or are ES modules (Bun honors it while Deno ignores it).
+    let package_type = match runtime.runtime {
+        Runtime::Bun => "module",
+        Runtime::Node | Runtime::Deno | Runtime::Embedded => "commonjs",
+    };
     std::fs::write(
         tests_root.join("js").join("package.json"),
-        "{\"type\": \"commonjs\"}",
+        format!("{{\"type\": \"{package_type}\"}}"),
     )
-    .context("Could not write the commonjs guide package.json")?;
+    .context("Could not write the module type guide package.json")?;
 
-    // Generate the supervisor Node module
-    let (supervisor_name, supervisor_template) = match run_options.runtime {
-        Runtime::Node => (
-            "node_supervisor.js",
-            include_template!("node_supervisor.js"),
-        ),
-        Runtime::Deno => (
-            "deno_supervisor.mjs",
-            include_template!("deno_supervisor.mjs"),
+    // Generate the reporter module
+    let (reporter_name, reporter_template) = match runtime.runtime {
+        Runtime::Node => ("node_reporter.js", include_template!("node_reporter.js")),
+        Runtime::Deno => ("deno_reporter.mjs", include_template!("deno_reporter.mjs")),
+        Runtime::Bun => ("bun_reporter.mjs", include_template!("bun_reporter.mjs")),
+        Runtime::Embedded => (
+            "embedded_reporter.js",
+            include_template!("embedded_reporter.js"),
         ),
     };
-    let supervisor_js_file = tests_root.join("js").join(supervisor_name);
+    let reporter_path = tests_root.join("js").join(reporter_name);
     let mut sorted_paths = modules_abs_paths.iter().collect::<Vec<_>>();
     sorted_paths.sort();
     crate::make::create_templated(
-        supervisor_template, // template
-        &supervisor_js_file, // output
+        reporter_template, // template
+        &reporter_path,    // output
         &[
-            ("{{ workersCount }}", &run_options.workers.to_string()),
-            ("{{ initialSeed }}", &run_options.seed.to_string()),
+            ("{{ initialSeed }}", &seed),
             ("{{ fuzzRuns }}", &run_options.fuzz.to_string()),
             ("{{ reporter }}", &run_options.reporter),
             ("{{ verbosity }}", &make_options.verbosity.to_string()),
@@ -200,13 +338,10 @@ fn main_helper(
             ("{{ polyfills }}", polyfills),
         ],
     )
-    .context(format!(
-        "Failed to write {}",
-        supervisor_js_file.display()
-    ))?;
+    .context(format!("Failed to write {}", reporter_path.display()))?;
 
     // For a Deno runtime, make deno_linereader.mjs and deno_logger.mjs available.
-    if let Runtime::Deno = run_options.runtime {
+    if let Runtime::Deno = runtime.runtime {
         let linereader_template = include_template!("deno_linereader.mjs");
         let linereader_path = tests_root.join("js").join("deno_linereader.mjs");
         std::fs::write(linereader_path, linereader_template)?;
@@ -215,84 +350,231 @@ fn main_helper(
         std::fs::write(logger_path, logger_template)?;
     }
 
-    // Start the tests supervisor
-    log::info!("Starting the supervisor ...");
-    let mut supervisor = match run_options.runtime {
-        Runtime::Node => {
-            let node_version = Command::new("node")
-                .arg("--version")
-                .output()
-                .context("\"node --version\" failed to start")?
-                .stdout;
-
-            // Node supports worker_threads as experimental feature since 10.5,
-            // but it is unknown whether all versions since 10.5 actually work with elm-test-rs.
-            let experimental_arg = if node_version.starts_with(b"v10.") {
-                Some("--experimental-worker")
-            } else {
-                None
-            };
+    // For a Bun runtime, make bun_logger.mjs available.
+    if let Runtime::Bun = runtime.runtime {
+        let logger_template = include_template!("bun_logger.mjs");
+        let logger_path = tests_root.join("js").join("bun_logger.mjs");
+        std::fs::write(logger_path, logger_template)?;
+    }
 
-            Command::new("node")
-                .args(experimental_arg)
-                .arg(supervisor_js_file)
-                .current_dir(tests_root)
-                .stdin(Stdio::piped())
-                .spawn()
-                .context("Node supervisor failed to start")?
-        }
-        Runtime::Deno => Command::new("deno")
-            .args(["run", "--allow-read", "--allow-hrtime"])
-            .arg(supervisor_js_file)
-            .current_dir(tests_root)
-            .stdin(Stdio::piped())
-            .spawn()
-            .context("Deno supervisor failed to start")?,
+    // The reporter of the previous run will be reused if it would be started from the same code.
+    let reporter_glue = fs::read_to_string(&reporter_path)
+        .context(format!("Failed to read {}", reporter_path.display()))?;
+    let reporter_key = hash_key((compiled_reporter_key, reporter_glue));
+    let spawner: Box<dyn Spawner + Send> = match runtime.runtime {
+        Runtime::Embedded => embedded_spawner(&tests_root.join("js"))?,
+        _ => Box::new(crate::supervisor::Processes {
+            program: runtime.executable.clone(),
+            args: runtime_args(runtime),
+            current_dir: tests_root,
+            runner: runner_path,
+            reporter: reporter_path,
+        }),
     };
+    Ok(Prepared::Ready(spawner, reporter_key))
+}
 
-    // Helper closure to write to supervisor
-    let stdin = supervisor
-        .stdin
-        .as_mut()
-        .context("Failed to open supervisor stdin")?;
-    let mut writeln = |msg| -> anyhow::Result<()> {
-        stdin.write_all(msg)?;
-        stdin.write_all(b"\n")?;
-        Ok(())
-    };
+/// Name of the file recording the key of the inputs Reporter.elm.js was compiled from.
+const REPORTER_KEY_FILE_NAME: &str = "Reporter.elm.js.key";
+
+/// The compilation of Reporter.elm into Reporter.elm.js.
+///
+/// It has its own root directory, with the dependencies of the tests, and its own elm-stuff,
+/// so that it can be compiled at the same time as Runner.elm.
+struct Reporter {
+    root: PathBuf,
+    elm_json: String,
+    compiled: PathBuf,
+    key_file: PathBuf,
+    key: String,
+    es_module: bool,
+}
+
+impl Reporter {
+    fn new(
+        tests_root: &Path,
+        make_options: &crate::make::Options,
+        runtime: Runtime,
+    ) -> anyhow::Result<Reporter> {
+        let tests_elm_json = fs::read_to_string(tests_root.join("elm.json"))
+            .context("Failed to read the elm.json of the tests")?;
+        let mut elm_json: serde_json::Value = serde_json::from_str(&tests_elm_json)
+            .context("Failed to parse the elm.json of the tests")?;
+        elm_json["source-directories"] = serde_json::json!(["src"]);
+        let elm_json = elm_json.to_string();
+        let compiler_version = make_options.compiler_version.to_string();
+        let key = crate::utils::stable_key([
+            std::env!("CARGO_PKG_VERSION"),
+            &elm_json,
+            include_template!("Reporter.elm"),
+            &make_options.compiler,
+            &compiler_version,
+            &format!("{runtime:?}"),
+        ]);
+        Ok(Reporter {
+            root: tests_root.join("reporter"),
+            elm_json,
+            compiled: tests_root.join("js").join("Reporter.elm.js"),
+            key_file: tests_root.join("js").join(REPORTER_KEY_FILE_NAME),
+            key,
+            es_module: matches!(runtime, Runtime::Deno | Runtime::Bun),
+        })
+    }
+
+    fn is_up_to_date(&self) -> bool {
+        let previous_key = fs::read_to_string(&self.key_file).unwrap_or_default();
+        previous_key.trim() == self.key && self.compiled.exists()
+    }
+
+    /// Write the elm.json and Reporter.elm to compile, and forget the previous key.
+    fn write_sources(&self) -> anyhow::Result<()> {
+        if self.key_file.exists() {
+            fs::remove_file(&self.key_file)
+                .context("Failed to remove the key of Reporter.elm.js")?;
+        }
+        fs::create_dir_all(self.root.join("src")).context(format!(
+            "Could not create the reporter dir {}",
+            self.root.display()
+        ))?;
+        fs::write(self.root.join("elm.json"), &self.elm_json)
+            .context("Failed to write the elm.json of the reporter")?;
+        fs::write(
+            self.root.join("src").join("Reporter.elm"),
+            include_template!("Reporter.elm"),
+        )
+        .context("Error writing Reporter.elm to test folder")
+    }
+
+    /// Compile Reporter.elm, capturing its errors.
+    fn compile(
+        &self,
+        elm_home: &Path,
+        make_options: &crate::make::Options,
+        cancel: &Cancel,
+    ) -> anyhow::Result<std::process::Output> {
+        crate::make::compile(
+            elm_home,
+            &self.root,             // current_dir
+            &make_options.compiler, // compiler
+            &self.compiled,         // output
+            &make_options.report,   // report
+            CompileMode::Default,   // mode
+            [Path::new("src").join("Reporter.elm")],
+            true, // capture_stderr
+            cancel,
+        )
+    }
 
-    // Send runner module path to supervisor to start the work
+    /// Post-process the compiled Reporter.elm.js and record the key of its inputs.
+    fn finish(&self) -> anyhow::Result<()> {
+        // For Deno and Bun runtimes, convert the compiled Reporter.elm.js into an ES module.
+        if self.es_module {
+            let compiled_reporter_code = fs::read_to_string(&self.compiled)?;
+            let patched = replace_console_log(&compiled_reporter_code)
+                .and_then(|elm_js| into_es_module(&elm_js))
+                .context("Failed to convert Reporter.elm.js into an ES module")?;
+            fs::write(&self.compiled, patched)?;
+        }
+        fs::write(&self.key_file, &self.key).context("Failed to write the key of Reporter.elm.js")
+    }
+}
+
+/// Path of the file with the key of the inputs of Runner.elm.js.
+fn runner_key_path(compiled_runner: &Path) -> PathBuf {
+    compiled_runner.with_file_name(crate::make::RUNNER_KEY_FILE_NAME)
+}
+
+/// Kernel patch applied to Runner.elm.js, recorded after the key of its inputs.
+fn applied_patch(compiled_runner: &Path) -> Option<String> {
+    let key = fs::read_to_string(runner_key_path(compiled_runner)).ok()?;
+    key.split_whitespace().nth(1).map(String::from)
+}
+
+/// Supervise the runners and reporter of prepared tests until they are done.
+/// Their spawner is kept in the session to rerun failed tests.
+pub fn run_prepared(
+    prepared: Prepared,
+    run_options: &Options,
+    cancel: &Cancel,
+    session: &mut Session,
+) -> anyhow::Result<i32> {
+    let (spawner, reporter_key) = match prepared {
+        Prepared::Done(exit_code) => return Ok(exit_code),
+        Prepared::Ready(spawner, reporter_key) => (spawner, reporter_key),
+    };
     log::info!("Running tests ...");
-    let runner_path_string = runner_path
-        .to_str()
-        .context(format!(
-            "Could not convert path into a String: {}",
-            runner_path.display()
-        ))?
-        .to_string();
-    writeln(runner_path_string.as_bytes())
-        .context("Failed to write runner path to supervisor stdin")?;
-
-    // Wait for supervisor child process to end and terminate with same exit code
-    let exit_code = wait_child(&mut supervisor);
-    Ok(exit_code.unwrap_or(0))
+    let supervisor_options = crate::supervisor::Options {
+        workers: run_options.workers,
+        timeout: run_options.timeout,
+        only: None,
+        cancel: cancel.clone(),
+    };
+    let exit_code = session
+        .supervisor
+        .run(spawner.as_ref(), reporter_key, &supervisor_options);
+    session.last_run = Some((spawner, reporter_key));
+    exit_code
 }
 
-/// Wait for child process to end
-fn wait_child(child: &mut std::process::Child) -> Option<i32> {
-    match child.try_wait() {
-        Ok(Some(status)) => status.code(),
-        Ok(None) => match child.wait() {
-            Ok(status) => status.code(),
-            _ => None,
+/// Rerun the tests that failed in the last run, without compiling anything.
+fn rerun_failures(
+    run_options: &Options,
+    cancel: &Cancel,
+    session: &mut Session,
+) -> anyhow::Result<i32> {
+    let failures = session.supervisor.failures().to_vec();
+    let Some((spawner, reporter_key)) = session.last_run.as_ref().filter(|_| !failures.is_empty())
+    else {
+        log::error!("No failed test to rerun");
+        return Ok(0);
+    };
+    let supervisor_options = crate::supervisor::Options {
+        workers: run_options.workers,
+        timeout: run_options.timeout,
+        only: Some(failures),
+        cancel: cancel.clone(),
+    };
+    session
+        .supervisor
+        .run(spawner.as_ref(), *reporter_key, &supervisor_options)
+}
+
+/// Arguments passed to the runtime executable before the script to run.
+fn runtime_args(runtime: &Detected) -> Vec<&'static str> {
+    match runtime.runtime {
+        Runtime::Node => vec![],
+        // High resolution time needs a permission before Deno 2, which removed the flag.
+        Runtime::Deno => match runtime.version {
+            Some(v) if v >= SemanticVersion::new(2, 0, 0) => vec!["run", "--allow-read"],
+            _ => vec!["run", "--allow-read", "--allow-hrtime"],
         },
-        Err(e) => {
-            log::error!("Error attempting to wait for child: {e}");
-            None
-        }
+        Runtime::Bun => vec!["run"],
+        Runtime::Embedded => vec![],
     }
 }
 
+/// Spawner of threads running the tests in the embedded QuickJS engine.
+#[cfg(feature = "embedded")]
+fn embedded_spawner(js_dir: &Path) -> anyhow::Result<Box<dyn Spawner + Send>> {
+    Ok(Box::new(crate::embedded::Threads::new(js_dir)?))
+}
+
+#[cfg(not(feature = "embedded"))]
+fn embedded_spawner(_js_dir: &Path) -> anyhow::Result<Box<dyn Spawner + Send>> {
+    anyhow::bail!("This elm-test-rs was built without the \"embedded\" feature")
+}
+
+/// Version of elm-explorations/test in the elm.json of the tests.
+fn testlib_version(tests_root: &Path) -> Option<String> {
+    let elm_json = fs::read_to_string(tests_root.join("elm.json")).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&elm_json).ok()?;
+    let version = ["dependencies", "test-dependencies"]
+        .iter()
+        .flat_map(|deps| ["direct", "indirect"].map(|kind| (deps, kind)))
+        .find_map(|(deps, kind)| config[deps][kind]["elm-explorations/test"].as_str())?;
+    Some(version.to_string())
+}
+
 /// Add a kernel patch to the generated code in order to be able to recognize
 /// values of type Test at runtime with the `check: a -> Maybe Test` function.
 ///
@@ -300,6 +582,10 @@ fn wait_child(child: &mut std::process::Child) -> Option<i32> {
 /// by a call to the "yet-to-be-defined" console.elmlog
 ///
 /// Transformation to an esmodule is also possible.
+///
+/// Every substitution is verified, so that a compiled code that does not have the expected shape,
+/// for example with another version of elm-explorations/test, is reported with a precise error
+/// instead of silently running no test.
 fn kernel_patch_tests(elm_js: &str, esmodule: bool) -> anyhow::Result<String> {
     // For older versions of elm-explorations/test we need to list every single
     // variant of the `Test` type. To avoid having to update this regex if a new
@@ -320,6 +606,17 @@ fn kernel_patch_tests(elm_js: &str, esmodule: bool) -> anyhow::Result<String> {
 "#,
     )?;
 
+    let variants_count = test_variant_definition.find_iter(elm_js).count();
+    if variants_count == 0 {
+        anyhow::bail!("No constructor of the variants of the Test type was found");
+    }
+    let checks_count = check_definition.find_iter(elm_js).count();
+    if checks_count != 1 {
+        anyhow::bail!(
+            "Expected exactly one definition of the check function of Runner.elm, found {checks_count}"
+        );
+    }
+
     let elm_js =
         test_variant_definition.replace_all(elm_js, "$0 __elmTestSymbol: __elmTestSymbol,");
     let elm_js = check_definition.replace(&elm_js, "$1 = value => value && value.__elmTestSymbol === __elmTestSymbol ? $$elm$$core$$Maybe$$Just(value) : $$elm$$core$$Maybe$$Nothing;");
@@ -327,35 +624,129 @@ fn kernel_patch_tests(elm_js: &str, esmodule: bool) -> anyhow::Result<String> {
     let elm_js = ["const __elmTestSymbol = Symbol('elmTestSymbol');", &elm_js].join("\n");
 
     // If an ES module is asked, the following transformation is applied.
+    let elm_js = replace_console_log(&elm_js)?;
     if esmodule {
-        Ok(into_es_module(&replace_console_log(&elm_js)))
+        into_es_module(&elm_js)
     } else {
-        Ok(replace_console_log(&elm_js))
+        Ok(elm_js)
     }
 }
 
-/// Replace console.log with console.elmlog and remove console.warn.
-fn replace_console_log(elm_js: &str) -> String {
-    // WARNING: this may fail if a user has this as a string somewhere
-    // and it is located before its definition by elm in the file.
-    let elm_js = elm_js.replacen(
-        "console.log(tag + ': ' + _Debug_toString(value));",
-        "console.elmlog(tag + ': ' + _Debug_toString(value));",
-        1,
+/// Replace console.log with console.elmlog in the definition of Debug.log,
+/// and remove the console.warn() about the compilation mode.
+fn replace_console_log(elm_js: &str) -> anyhow::Result<String> {
+    // Only look for console.log in the definition of _Debug_log, not in strings of user code.
+    let debug_log_definition = Regex::new(
+        r#"(?mx)
+    ^(var\s+_Debug_log\s*=\s*F2\(\s*function\s*\(\s*tag\s*,\s*value\s*\)\s*\{\s*)
+    console\.log\(tag\s*\+\s*':\s'\s*\+\s*_Debug_toString\(value\)\);
+"#,
+    )?;
+    let logging_debug_log = Regex::new(r"(?m)^var\s+_Debug_log\s*=\s*F2\([^;]*console\.log\(")?;
+    let hooks_count = debug_log_definition.find_iter(elm_js).count();
+    // Debug.log is only defined when used, and it does not log with --optimize.
+    if hooks_count != logging_debug_log.find_iter(elm_js).count() || hooks_count > 1 {
+        anyhow::bail!("The console.log call in the definition of Debug.log could not be replaced");
+    }
+    let elm_js = debug_log_definition.replace(
+        elm_js,
+        "${1}console.elmlog(tag + ': ' + _Debug_toString(value));",
     );
     // Remove the console.warn() at the begining due to not compiling with --optimize
-    elm_js.replacen("console.warn", "", 1)
+    Ok(elm_js.replacen("console.warn('Compiled in", "('Compiled in", 1))
 }
 
-/// Convert an JS file resulting from an Elm compilation into an ES module.
-fn into_es_module(elm_js: &str) -> String {
+/// Convert an JS file resulting from an Elm compilation into an ES module.
+fn into_es_module(elm_js: &str) -> anyhow::Result<String> {
     // replace '}(this));' by '}(scope));' at the end.
-    let last_this_offset = elm_js.rfind("this").unwrap();
-    [
+    let last_this_offset = elm_js
+        .rfind("}(this));")
+        .context("The end of the compiled code, \"}(this));\", was not found")?
+        + 2;
+    Ok([
         "const scope = {};",
         &elm_js[..last_this_offset],
         "scope));",
         "export const { Elm } = scope;",
     ]
-    .join("\n")
+    .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::kernel_patch_tests;
+    use std::path::Path;
+
+    /// The kernel patch of the compiled Runner.elm.js of each elm-explorations/test version
+    /// and compilation mode in tests/kernel-patch/ gives the .expected.js file next to it.
+    /// Run the test with UPDATE_EXPECTED=1 to write the .expected.js files.
+    #[test]
+    fn kernel_patch_snapshots() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/kernel-patch");
+        let mut modes = Vec::new();
+        for entry in std::fs::read_dir(&fixtures).unwrap() {
+            let file = entry.unwrap().path();
+            let name = file.file_name().unwrap().to_string_lossy().into_owned();
+            let Some(stem) = name.strip_suffix(".js") else {
+                continue;
+            };
+            if stem.ends_with(".expected") {
+                continue;
+            }
+            let compiled = std::fs::read_to_string(&file).unwrap();
+            let patched = kernel_patch_tests(&compiled, false).unwrap();
+            let expected_file = fixtures.join(format!("{stem}.expected.js"));
+            if std::env::var_os("UPDATE_EXPECTED").is_some() {
+                std::fs::write(&expected_file, &patched).unwrap();
+            }
+            let expected = std::fs::read_to_string(&expected_file).unwrap();
+            assert_eq!(patched, expected, "{}", file.display());
+            modes.extend(stem.rsplit_once('-').map(|(_, mode)| mode.to_string()));
+        }
+        // Every mode of --compile-mode is covered by at least one compiled file.
+        for mode in ["default", "optimize", "debug"] {
+            assert!(
+                modes.iter().any(|m| m == mode),
+                "No {mode} file in {}",
+                fixtures.display()
+            );
+        }
+    }
+
+    #[test]
+    fn kernel_patch_es_module() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/kernel-patch/elm-explorations-test-2.0.0-default.js");
+        let compiled = std::fs::read_to_string(fixture).unwrap();
+        let patched = kernel_patch_tests(&compiled, true).unwrap();
+        assert!(patched.starts_with("const scope = {};"));
+        assert!(patched.ends_with("}(\nscope));\nexport const { Elm } = scope;"));
+        assert!(kernel_patch_tests(&compiled.replace("(this)", "(self)"), true).is_err());
+    }
+
+    #[test]
+    fn kernel_patch_failures() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/kernel-patch/elm-explorations-test-2.0.0-default.js");
+        let compiled = std::fs::read_to_string(fixture).unwrap();
+        let error = |elm_js: &str| kernel_patch_tests(elm_js, false).unwrap_err().to_string();
+        assert_eq!(
+            error(&compiled.replace("ElmTestVariant__", "Variant")),
+            "No constructor of the variants of the Test type was found"
+        );
+        assert_eq!(
+            error(&compiled.replace("checkHelperReplaceMe___;", "somethingElse;")),
+            "Expected exactly one definition of the check function of Runner.elm, found 0"
+        );
+        let check =
+            "var $author$project$Runner$check = $author$project$Runner$checkHelperReplaceMe___;";
+        assert_eq!(
+            error(&compiled.replace(check, &format!("{check}\n{check}"))),
+            "Expected exactly one definition of the check function of Runner.elm, found 2"
+        );
+        assert_eq!(
+            error(&compiled.replace("console.log(tag + ': '", "console.log(tag + ':  '")),
+            "The console.log call in the definition of Debug.log could not be replaced"
+        );
+    }
 }
@@ -0,0 +1,192 @@
+//! Module dealing with finding the JavaScript runtime used to run the tests.
+
+use anyhow::Context;
+use pubgrub::version::SemanticVersion;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The runtime to be used.
+pub enum Runtime {
+    /// Node is the default runtime.
+    Node,
+    /// Deno is an alternative runtime.
+    Deno,
+    /// Bun is an alternative runtime, compatible with the Node glue code.
+    Bun,
+    /// QuickJS engine embedded in elm-test-rs with the "embedded" feature.
+    Embedded,
+}
+
+impl Runtime {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Runtime::Node => "Node",
+            Runtime::Deno => "Deno",
+            Runtime::Bun => "Bun",
+            Runtime::Embedded => "QuickJS",
+        }
+    }
+
+    /// Oldest version known to work with elm-test-rs.
+    ///
+    /// The glue code of runners and the reporter reads JSON lines on stdin and writes them on stdout.
+    /// Node needs the `performance` timer of perf_hooks, available since 8.5,
+    /// and Bun only reads stdin with node:readline reliably since 1.0.
+    pub fn minimum_version(&self) -> SemanticVersion {
+        match self {
+            Runtime::Node => SemanticVersion::new(8, 5, 0),
+            Runtime::Deno => SemanticVersion::new(1, 0, 0),
+            Runtime::Bun => SemanticVersion::new(1, 0, 0),
+            Runtime::Embedded => SemanticVersion::zero(),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Paths to the runtimes executables, or bare commands to look up in PATH.
+pub struct Executables {
+    pub node: String,
+    pub deno: String,
+    pub bun: String,
+}
+
+#[derive(Debug)]
+/// A runtime found on this machine.
+pub struct Detected {
+    pub runtime: Runtime,
+    pub executable: PathBuf,
+    /// None if the output of `--version` could not be understood.
+    pub version: Option<SemanticVersion>,
+}
+
+/// Find the runtime to run the tests with and check its version.
+///
+/// If no runtime was explicitly requested, Node is preferred,
+/// but Deno, then Bun, then the embedded engine (if available)
+/// are used instead if Node cannot be found.
+pub fn detect(requested: Option<Runtime>, executables: &Executables) -> anyhow::Result<Detected> {
+    let fallbacks: &[Runtime] = if cfg!(feature = "embedded") {
+        &[Runtime::Deno, Runtime::Bun, Runtime::Embedded]
+    } else {
+        &[Runtime::Deno, Runtime::Bun]
+    };
+    let detected = match requested {
+        Some(runtime) => probe(runtime, executables)?,
+        None => match probe(Runtime::Node, executables) {
+            Ok(detected) => detected,
+            Err(node_err) => fallbacks
+                .iter()
+                .find_map(|&runtime| probe(runtime, executables).ok())
+                .inspect(|detected| {
+                    log::error!(
+                        "Node was not found, running the tests with {} instead",
+                        detected.runtime.name()
+                    )
+                })
+                .ok_or(node_err)
+                .context("No JavaScript runtime was found. Install Node, Deno or Bun, or give the path to one with --node, --deno-path or --bun-path")?,
+        },
+    };
+
+    // Report the runtime and whether it meets minimum requirements.
+    let name = detected.runtime.name();
+    let minimum = detected.runtime.minimum_version();
+    match detected.version {
+        None if detected.runtime == Runtime::Embedded => {
+            log::warn!("Running the tests with the embedded {name} engine")
+        }
+        None => log::warn!(
+            "Could not figure out the version of {name} at {}, it must be {minimum} or newer",
+            detected.executable.display()
+        ),
+        Some(version) if version < minimum => anyhow::bail!(
+            "{name} {version} at {} is not supported, please upgrade to {name} {minimum} or newer",
+            detected.executable.display()
+        ),
+        Some(version) => log::warn!(
+            "Running the tests with {name} {version} at {} (minimum required: {minimum})",
+            detected.executable.display()
+        ),
+    }
+    Ok(detected)
+}
+
+/// Find the executable of a runtime and ask for its version.
+fn probe(runtime: Runtime, executables: &Executables) -> anyhow::Result<Detected> {
+    let executable = match runtime {
+        Runtime::Node => &executables.node,
+        Runtime::Deno => &executables.deno,
+        Runtime::Bun => &executables.bun,
+        Runtime::Embedded => {
+            return Ok(Detected {
+                runtime,
+                executable: std::env::current_exe()
+                    .context("Failed to get the path of elm-test-rs")?,
+                version: None,
+            })
+        }
+    };
+    let context_if_fails = format!(
+        "Failed to find {} with \"{executable}\". Are you sure it's in your PATH?",
+        runtime.name()
+    );
+    let executable = which::CanonicalPath::new(executable)
+        .context(context_if_fails)?
+        .as_path()
+        .to_path_buf();
+    log::debug!("We found an executable: {}", executable.display());
+    let output = Command::new(&executable)
+        .arg("--version")
+        .output()
+        .context(format!(
+            "\"{} --version\" failed to start",
+            executable.display()
+        ))?;
+    let version = parse_version(&String::from_utf8_lossy(&output.stdout));
+    Ok(Detected {
+        runtime,
+        executable,
+        version,
+    })
+}
+
+/// Extract the first version number of the output of `--version`,
+/// such as "v18.12.1" for Node, "deno 1.28.3 (release, ...)" for Deno
+/// or "1.0.2" for Bun.
+fn parse_version(output: &str) -> Option<SemanticVersion> {
+    output.split_whitespace().find_map(|word| {
+        let number: String = word
+            .trim_start_matches('v')
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        SemanticVersion::from_str(&number).ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pubgrub::version::SemanticVersion;
+
+    #[test]
+    fn parse_version() {
+        let asrt_eq = |output: &str, (major, minor, patch)| {
+            assert_eq!(
+                super::parse_version(output),
+                Some(SemanticVersion::new(major, minor, patch))
+            )
+        };
+        asrt_eq("v18.12.1\n", (18, 12, 1));
+        asrt_eq("v10.5.0", (10, 5, 0));
+        asrt_eq(
+            "deno 1.28.3 (release, x86_64-unknown-linux-gnu)\nv8 10.9.194.5\ntypescript 4.8.3\n",
+            (1, 28, 3),
+        );
+        asrt_eq("1.0.2\n", (1, 0, 2));
+        asrt_eq("1.1.9-canary.1+abc\n", (1, 1, 9));
+        assert_eq!(super::parse_version("not a version"), None);
+        assert_eq!(super::parse_version(""), None);
+    }
+}
@@ -0,0 +1,637 @@
+//! Module supervising the programs running the tests.
+//!
+//! The supervisor owns the queue of tests, dispatches them to runners,
+//! forwards their results to the reporter, and returns the exit code chosen by the reporter.
+//! Runners and the reporter are JS programs exchanging JSON messages with the supervisor,
+//! one message per line. They run in processes of the chosen runtime (Node, Deno, Bun),
+//! or in threads of the embedded engine.
+
+use anyhow::Context;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use crate::utils::Cancel;
+
+/// Longest time waiting for an event before checking timeouts and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+/// Options of the supervisor.
+pub struct Options {
+    /// Maximum number of runners working in parallel.
+    pub workers: u32,
+    /// Maximum duration of a single test, None if unlimited.
+    pub timeout: Option<Duration>,
+    /// Ids of the tests to run, None to run all of them.
+    pub only: Option<Vec<u64>>,
+    /// Stop the run early when raised (in watch mode).
+    pub cancel: Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Sender of a message to the supervisor.
+/// Ids are unique among all programs started by a supervisor.
+pub enum Source {
+    Reporter(usize),
+    Runner(usize),
+}
+
+#[derive(Debug)]
+/// Message received by the supervisor.
+pub enum Event {
+    /// A line sent by a runner or the reporter, normally containing a JSON message.
+    Line(Source, String),
+    /// A runner or the reporter stopped, with the reason if known.
+    Stopped(Source, Option<String>),
+}
+
+/// A runner or reporter program started by a Spawner.
+pub trait Worker {
+    /// Send a JSON message to the program.
+    fn send(&mut self, msg: &str) -> anyhow::Result<()>;
+    /// Stop the program, even if it is still working.
+    fn stop(&mut self);
+}
+
+/// Something able to start runner and reporter programs.
+pub trait Spawner {
+    /// Start a program sending its messages as events tagged with the given source.
+    fn spawn(&self, source: Source, events: &Sender<Event>) -> anyhow::Result<Box<dyn Worker>>;
+}
+
+/// Spawner of runtime processes, reading messages on stdin and writing them on stdout.
+pub struct Processes {
+    /// Runtime executable.
+    pub program: PathBuf,
+    /// Runtime arguments placed before the JS file.
+    pub args: Vec<&'static str>,
+    /// Working directory of the processes.
+    pub current_dir: PathBuf,
+    /// JS file of the runners.
+    pub runner: PathBuf,
+    /// JS file of the reporter.
+    pub reporter: PathBuf,
+}
+
+impl Spawner for Processes {
+    fn spawn(&self, source: Source, events: &Sender<Event>) -> anyhow::Result<Box<dyn Worker>> {
+        let js_file = match source {
+            Source::Reporter(_) => &self.reporter,
+            Source::Runner(_) => &self.runner,
+        };
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(js_file)
+            .current_dir(&self.current_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context(format!("Failed to start {}", js_file.display()))?;
+        let stdin = child
+            .stdin
+            .take()
+            .context("Failed to open the stdin of a child")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("Failed to open the stdout of a child")?;
+
+        // Forward all lines written on stdout until the process ends
+        let events = events.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if events.send(Event::Line(source, line)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        let _ = events.send(Event::Stopped(source, Some(err.to_string())));
+                        return;
+                    }
+                }
+            }
+            let _ = events.send(Event::Stopped(source, None));
+        });
+        Ok(Box::new(Process { child, stdin }))
+    }
+}
+
+/// A runtime process started by the Processes spawner.
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl Worker for Process {
+    fn send(&mut self, msg: &str) -> anyhow::Result<()> {
+        writeln!(self.stdin, "{msg}")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A runner with the test it is currently running, and since when.
+struct Runner {
+    id: usize,
+    worker: Box<dyn Worker>,
+    running: Option<(u64, Instant)>,
+}
+
+impl Runner {
+    /// Ask the runner to run the next test, if any.
+    fn dispatch_work(&mut self, todo_tests: &mut Vec<u64>) -> anyhow::Result<()> {
+        if let Some(id) = todo_tests.pop() {
+            self.worker
+                .send(&format!(r#"{{"type_":"runTest","id":{id}}}"#))?;
+            self.running = Some((id, Instant::now()));
+        }
+        Ok(())
+    }
+}
+
+/// Supervisor of the runners and the reporter.
+///
+/// Runners are started for each run, but the reporter is kept alive between runs
+/// (in watch mode) as long as it is started from the same sources.
+pub struct Supervisor {
+    events_sender: Sender<Event>,
+    events: Receiver<Event>,
+    /// The reporter, with its id and the key of the sources it was started from.
+    reporter: Option<(usize, Box<dyn Worker>, u64)>,
+    /// Ids are never reused, so that late messages from stopped programs can be ignored.
+    next_id: usize,
+    /// Ids of the tests that failed in the last run.
+    failures: Vec<u64>,
+}
+
+impl Supervisor {
+    pub fn new() -> Supervisor {
+        let (events_sender, events) = channel();
+        Supervisor {
+            events_sender,
+            events,
+            reporter: None,
+            next_id: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Ids of the tests that failed in the last run.
+    pub fn failures(&self) -> &[u64] {
+        &self.failures
+    }
+
+    /// Run all the tests and return the exit code chosen by the reporter.
+    ///
+    /// The first runner is asked for the number of tests,
+    /// then tests are dispatched one by one to up to `options.workers` runners.
+    /// Every result is forwarded to the reporter, until it signals that it has finished.
+    /// Without any test to run, the reporter is only told so and finishes on its own.
+    /// The reporter of the previous run is reused if `reporter_key` did not change.
+    pub fn run(
+        &mut self,
+        spawner: &dyn Spawner,
+        reporter_key: u64,
+        options: &Options,
+    ) -> anyhow::Result<i32> {
+        let start_time = Instant::now();
+        match &self.reporter {
+            Some((_, _, key)) if *key == reporter_key => log::info!("Reusing the reporter ..."),
+            _ => {
+                self.stop_reporter();
+                let id = self.new_id();
+                let worker = spawner.spawn(Source::Reporter(id), &self.events_sender)?;
+                self.reporter = Some((id, worker, reporter_key));
+            }
+        }
+        let mut runners = Vec::new();
+        self.failures.clear();
+        let result = self.supervise(spawner, options, &mut runners);
+
+        // Stop all runners, even if they are still working after an error.
+        // The reporter is in an unknown state after an error so it is stopped too.
+        runners.iter_mut().for_each(|runner| runner.worker.stop());
+        if result.is_err() {
+            self.stop_reporter();
+        }
+        log::warn!(
+            "Running duration (since the supervisor started): {} ms\n",
+            start_time.elapsed().as_millis()
+        );
+        result
+    }
+
+    fn new_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn stop_reporter(&mut self) {
+        if let Some((_, mut worker, _)) = self.reporter.take() {
+            worker.stop();
+        }
+    }
+
+    /// Supervisor loop, handling events until the reporter has finished.
+    fn supervise(
+        &mut self,
+        spawner: &dyn Spawner,
+        options: &Options,
+        runners: &mut Vec<Runner>,
+    ) -> anyhow::Result<i32> {
+        // Start the first runner and ask it the number of tests
+        let id = self.new_id();
+        runners.push(Runner {
+            id,
+            worker: spawner.spawn(Source::Runner(id), &self.events_sender)?,
+            running: None,
+        });
+        runners[0]
+            .worker
+            .send(r#"{"type_":"askTestsCount"}"#)
+            .context("Failed to ask the tests count")?;
+        let mut todo_tests: Vec<u64> = Vec::new();
+
+        // Wake up regularly to check timeouts and cancellation.
+        let poll_interval = options
+            .timeout
+            .map_or(POLL_INTERVAL, |timeout| timeout.min(POLL_INTERVAL));
+        loop {
+            let event = match self.events.recv_timeout(poll_interval) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    anyhow::bail!("The supervisor stopped receiving messages")
+                }
+            };
+            if let Some(timeout) = options.timeout {
+                check_timeouts(runners, timeout)?;
+            }
+            options.cancel.check()?;
+            let (reporter_id, reporter, _) = self
+                .reporter
+                .as_mut()
+                .context("The reporter is not started")?;
+            match event {
+                None => {}
+                Some(Event::Line(Source::Runner(runner_id), line)) => {
+                    let Some(runner_index) = runners.iter().position(|r| r.id == runner_id) else {
+                        log::debug!("Ignoring a message from a stopped runner: {line}");
+                        continue;
+                    };
+                    let msg = match serde_json::from_str::<serde_json::Value>(&line) {
+                        Ok(msg) => msg,
+                        Err(_) => {
+                            // Not a message, probably something printed by the tests
+                            eprintln!("{line}");
+                            continue;
+                        }
+                    };
+                    match msg["type_"].as_str() {
+                        Some("testsCount") => {
+                            print_setup_logs(&msg["logs"]);
+                            let tests_count = msg["testsCount"].as_u64().unwrap_or(0);
+                            todo_tests = match &options.only {
+                                None => (0..tests_count).rev().collect(),
+                                Some(ids) => ids
+                                    .iter()
+                                    .rev()
+                                    .filter(|id| **id < tests_count)
+                                    .copied()
+                                    .collect(),
+                            };
+
+                            // Reset reporter, with the number of tests actually run
+                            let mut msg = msg;
+                            msg["testsCount"] = todo_tests.len().into();
+                            reporter
+                                .send(&msg.to_string())
+                                .context("Failed to send the tests count to the reporter")?;
+
+                            // The reporter writes its summary and exit code for zero tests.
+                            if todo_tests.is_empty() {
+                                if tests_count == 0 {
+                                    log::error!(
+                                        "No exposed values of type Test was found. Did you forget to expose them?"
+                                    );
+                                } else {
+                                    log::error!("None of the selected tests exist anymore");
+                                }
+                                continue;
+                            }
+
+                            // Send work to all runners, starting them as needed
+                            runners[0].dispatch_work(&mut todo_tests)?;
+                            let max_runners = (todo_tests.len() + 1).min(options.workers as usize);
+                            for _ in 1..max_runners {
+                                let id = self.new_id();
+                                let mut runner = Runner {
+                                    id,
+                                    worker: spawner
+                                        .spawn(Source::Runner(id), &self.events_sender)?,
+                                    running: None,
+                                };
+                                runner.dispatch_work(&mut todo_tests)?;
+                                runners.push(runner);
+                            }
+                        }
+                        Some("testResult") => {
+                            let runner = &mut runners[runner_index];
+                            if let Some((test_id, _)) = runner.running.take() {
+                                if is_failure(&msg["result"]) {
+                                    self.failures.push(test_id);
+                                }
+                            }
+                            runner.dispatch_work(&mut todo_tests)?;
+                            reporter
+                                .send(&line)
+                                .context("Failed to send a test result to the reporter")?;
+                        }
+                        _ => log::error!("Invalid runner msg.type_: {}", msg["type_"]),
+                    }
+                }
+                Some(Event::Line(Source::Reporter(id), line)) if id == *reporter_id => {
+                    let msg: serde_json::Value = serde_json::from_str(&line)
+                        .context(format!("Invalid message from the reporter: {line}"))?;
+                    match msg["type_"].as_str() {
+                        Some("stdout") => {
+                            print!("{}", msg["content"].as_str().unwrap_or_default());
+                            std::io::stdout().flush()?;
+                        }
+                        Some("finished") => return Ok(msg["exitCode"].as_i64().unwrap_or(1) as i32),
+                        _ => log::error!("Invalid reporter msg.type_: {}", msg["type_"]),
+                    }
+                }
+                Some(Event::Stopped(Source::Reporter(id), reason)) if id == *reporter_id => {
+                    let reason = reason.map(|r| format!(": {r}")).unwrap_or_default();
+                    anyhow::bail!("The reporter stopped unexpectedly{reason}")
+                }
+                Some(Event::Stopped(Source::Runner(id), reason)) => {
+                    let reason = reason.map(|r| format!(": {r}")).unwrap_or_default();
+                    match runners.iter().find(|r| r.id == id).map(|r| r.running) {
+                        None => log::debug!("Runner {id} stopped{reason}"),
+                        Some(Some((test_id, _))) => anyhow::bail!(
+                            "Test runner {id} stopped unexpectedly while running test #{test_id}{reason}"
+                        ),
+                        Some(None) => anyhow::bail!("Test runner {id} stopped unexpectedly{reason}"),
+                    }
+                }
+                Some(event) => log::debug!("Ignoring an event from a stopped reporter: {event:?}"),
+            }
+        }
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop_reporter();
+    }
+}
+
+/// Check if a test result, as encoded by elm-test-runner, is a failure.
+fn is_failure(result: &serde_json::Value) -> bool {
+    match result {
+        serde_json::Value::Array(results) => results.iter().any(is_failure),
+        result => result["type"] == "Failed",
+    }
+}
+
+/// Fail if a runner has been running the same test for too long.
+fn check_timeouts(runners: &[Runner], timeout: Duration) -> anyhow::Result<()> {
+    for runner in runners {
+        if let Some((test_id, start)) = runner.running {
+            if start.elapsed() > timeout {
+                anyhow::bail!(
+                    "Test #{test_id} in runner {} did not finish within {} s. Use --timeout to allow more time.",
+                    runner.id,
+                    timeout.as_secs_f32()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print debug logs captured when setting up tests.
+fn print_setup_logs(logs: &serde_json::Value) {
+    let logs = logs.as_array().map(Vec::as_slice).unwrap_or_default();
+    if !logs.is_empty() {
+        eprintln!("Debug logs captured when setting up tests: -----------\n");
+        logs.iter()
+            .filter_map(|log| log.as_str())
+            .for_each(|log| eprint!("{log}"));
+        eprintln!("\n------------------------------------------------------\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Options, Source, Spawner, Supervisor, Worker};
+    use crate::utils::{Cancel, Cancelled};
+    use std::cell::Cell;
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
+
+    /// Fake runners and reporter answering in the same thread.
+    /// Runners never answer the test `hang_on`, stop on the test `crash_on`,
+    /// and tests with an id multiple of 3 fail.
+    struct Fake {
+        tests_count: u64,
+        hang_on: Option<u64>,
+        crash_on: Option<u64>,
+        reporters_count: Cell<u32>,
+    }
+
+    struct FakeWorker {
+        source: Source,
+        events: Sender<Event>,
+        tests_count: u64,
+        expected: u64,
+        received: u64,
+        hang_on: Option<u64>,
+        crash_on: Option<u64>,
+    }
+
+    fn fake(hang_on: Option<u64>, crash_on: Option<u64>) -> Fake {
+        Fake {
+            tests_count: 10,
+            hang_on,
+            crash_on,
+            reporters_count: Cell::new(0),
+        }
+    }
+
+    impl Spawner for Fake {
+        fn spawn(&self, source: Source, events: &Sender<Event>) -> anyhow::Result<Box<dyn Worker>> {
+            if let Source::Reporter(_) = source {
+                self.reporters_count.set(self.reporters_count.get() + 1);
+            }
+            Ok(Box::new(FakeWorker {
+                source,
+                events: events.clone(),
+                tests_count: self.tests_count,
+                expected: 0,
+                received: 0,
+                hang_on: self.hang_on,
+                crash_on: self.crash_on,
+            }))
+        }
+    }
+
+    impl Worker for FakeWorker {
+        fn send(&mut self, msg: &str) -> anyhow::Result<()> {
+            let msg: serde_json::Value = serde_json::from_str(msg)?;
+            let events = self.events.clone();
+            let source = self.source;
+            let answer = |line: String| events.send(Event::Line(source, line));
+            match (self.source, msg["type_"].as_str().unwrap()) {
+                (Source::Runner(_), "askTestsCount") => answer(format!(
+                    r#"{{"type_":"testsCount","testsCount":{},"logs":[]}}"#,
+                    self.tests_count
+                ))?,
+                (Source::Runner(_), "runTest") => {
+                    let id = msg["id"].as_u64().unwrap();
+                    if Some(id) == self.crash_on {
+                        events.send(Event::Stopped(source, None))?;
+                    } else if Some(id) != self.hang_on {
+                        let outcome = if id % 3 == 0 { "Failed" } else { "Passed" };
+                        answer(format!(
+                            r#"{{"type_":"testResult","id":{id},"result":{{"type":"{outcome}"}}}}"#
+                        ))?;
+                    }
+                }
+                (Source::Reporter(_), "testsCount") => {
+                    self.expected = msg["testsCount"].as_u64().unwrap();
+                    self.received = 0;
+                    // Like the elm-test-runner reporter, finish right away without tests
+                    if self.expected == 0 {
+                        answer(r#"{"type_":"finished","exitCode":2}"#.to_string())?;
+                    }
+                }
+                (Source::Reporter(_), "testResult") => {
+                    self.received += 1;
+                    answer(r#"{"type_":"stdout","content":""}"#.to_string())?;
+                    if self.received == self.expected {
+                        answer(r#"{"type_":"finished","exitCode":3}"#.to_string())?;
+                    }
+                }
+                _ => panic!("Unexpected message {msg}"),
+            }
+            Ok(())
+        }
+
+        fn stop(&mut self) {
+            // Like real programs, stopped ones may still send events
+            let _ = self.events.send(Event::Stopped(self.source, None));
+        }
+    }
+
+    fn options(timeout: Option<Duration>) -> Options {
+        Options {
+            workers: 4,
+            timeout,
+            only: None,
+            cancel: Cancel::default(),
+        }
+    }
+
+    #[test]
+    fn exit_code_from_reporter() {
+        let mut supervisor = Supervisor::new();
+        assert_eq!(
+            supervisor
+                .run(&fake(None, None), 0, &options(None))
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn crashed_runner() {
+        let mut supervisor = Supervisor::new();
+        let err = supervisor.run(&fake(None, Some(5)), 0, &options(None));
+        let err = err.unwrap_err().to_string();
+        assert!(err.contains("while running test #5"), "{err}");
+    }
+
+    #[test]
+    fn timeout() {
+        let mut supervisor = Supervisor::new();
+        let timeout = Some(Duration::from_millis(10));
+        let err = supervisor.run(&fake(Some(7), None), 0, &options(timeout));
+        let err = err.unwrap_err().to_string();
+        assert!(err.contains("Test #7"), "{err}");
+    }
+
+    #[test]
+    fn reuse_reporter() {
+        let mut supervisor = Supervisor::new();
+        let fake = fake(None, None);
+        for key in [0, 0, 0, 1, 1] {
+            assert_eq!(supervisor.run(&fake, key, &options(None)).unwrap(), 3);
+        }
+        assert_eq!(fake.reporters_count.get(), 2);
+
+        // The reporter is restarted after a failed run
+        let crashing = self::fake(None, Some(5));
+        assert!(supervisor.run(&crashing, 1, &options(None)).is_err());
+        assert_eq!(supervisor.run(&fake, 1, &options(None)).unwrap(), 3);
+        assert_eq!(fake.reporters_count.get(), 3);
+    }
+
+    #[test]
+    fn rerun_failures() {
+        let mut supervisor = Supervisor::new();
+        let fake = fake(None, None);
+        assert_eq!(supervisor.run(&fake, 0, &options(None)).unwrap(), 3);
+        let mut failures = supervisor.failures().to_vec();
+        failures.sort();
+        assert_eq!(failures, [0, 3, 6, 9]);
+
+        // Only run some of the tests
+        let only = Options {
+            only: Some(vec![1, 3, 42]),
+            ..options(None)
+        };
+        assert_eq!(supervisor.run(&fake, 0, &only).unwrap(), 3);
+        assert_eq!(supervisor.failures(), [3]);
+    }
+
+    #[test]
+    fn no_tests_to_run() {
+        let mut supervisor = Supervisor::new();
+        let empty = Fake {
+            tests_count: 0,
+            ..fake(None, None)
+        };
+        assert_eq!(supervisor.run(&empty, 0, &options(None)).unwrap(), 2);
+
+        // Selected tests that do not exist anymore, with the same reporter
+        let fake = fake(None, None);
+        let only = Options {
+            only: Some(vec![42]),
+            ..options(None)
+        };
+        assert_eq!(supervisor.run(&fake, 0, &only).unwrap(), 2);
+        assert_eq!(supervisor.run(&fake, 0, &options(None)).unwrap(), 3);
+    }
+
+    #[test]
+    fn cancelled() {
+        let mut supervisor = Supervisor::new();
+        let cancelled = options(None);
+        cancelled.cancel.cancel();
+        let err = supervisor.run(&fake(Some(7), None), 0, &cancelled);
+        assert!(err.unwrap_err().is::<Cancelled>());
+    }
+}
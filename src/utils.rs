@@ -3,11 +3,17 @@
 use anyhow::Context;
 use path_absolutize::Absolutize;
 use pubgrub::version::SemanticVersion;
+use std::collections::hash_map::DefaultHasher;
 use std::error::Error;
-use std::io::Write;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, Command, Output};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 #[macro_export]
 #[cfg(unix)]
@@ -102,6 +108,33 @@ pub fn json_write<P: AsRef<Path>, T: ?Sized + serde::Serialize>(
     writer.flush().map_err(|e| e.into())
 }
 
+/// Hash some inputs into a key to know if they changed since last time.
+pub fn hash_key(inputs: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    inputs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash some inputs into a key written to disk, to know if they changed since a previous run.
+///
+/// Unlike [`hash_key`], the key is stable across Rust releases.
+/// It is the 64 bits FNV-1a hash of all inputs, each prefixed by its length.
+pub fn stable_key<I: AsRef<[u8]>>(inputs: impl IntoIterator<Item = I>) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    for input in inputs {
+        let input = input.as_ref();
+        write(&(input.len() as u64).to_le_bytes());
+        write(input);
+    }
+    format!("{hash:016x}")
+}
+
 /// Returns the absolute path with a useful error message if not possible.
 pub fn absolute_path<P: AsRef<Path>>(path: P) -> anyhow::Result<PathBuf> {
     let path = path.as_ref();
@@ -153,3 +186,108 @@ pub fn compiler_command(compiler: &str, executable: &Path) -> Command {
     }
     Command::new(executable)
 }
+
+#[derive(Debug, Default, Clone)]
+/// Flag raised by the watcher when newer changes make the current run obsolete.
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// Return a [`Cancelled`] error if the flag was raised.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.0.load(Ordering::Relaxed) {
+            Err(Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Error of a run stopped early because it was cancelled.
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The run was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// Wait for a child process like [`Command::output`], but kill it if `cancel` is raised.
+pub fn wait_cancellable(mut child: Child, cancel: &Cancel) -> anyhow::Result<Output> {
+    // Read the pipes in threads so that the child never blocks on a full pipe.
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for a process")? {
+            break status;
+        }
+        if let Err(cancelled) = cancel.check() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(cancelled);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// Temporary directory for unit tests, with a unique name, removed when dropped.
+#[cfg(test)]
+pub struct TestDir(tempfile::TempDir);
+
+#[cfg(test)]
+impl TestDir {
+    pub fn new() -> TestDir {
+        TestDir(tempfile::tempdir().unwrap())
+    }
+
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+
+    /// Write a file at a path relative to the directory, creating its parent directories.
+    pub fn write(&self, path: &str, content: &str) -> PathBuf {
+        let file = self.path().join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, content).unwrap();
+        file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn stable_key() {
+        // Keys written by previous runs must stay valid.
+        assert_eq!(super::stable_key([""; 0]), "cbf29ce484222325");
+        assert_eq!(super::stable_key(["elm", "0.19.1"]), "df5c3699d999d549");
+        assert_ne!(
+            super::stable_key(["ab", "c"]),
+            super::stable_key(["a", "bc"])
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Module dealing with the tests of multiple Elm projects at once,
+//! such as all the projects of a monorepo.
+//!
+//! Each project is prepared and run exactly like a single one, with its own configuration,
+//! then a summary of all the projects is printed.
+
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::deps::ConnectivityStrategy;
+use crate::make::Output;
+use crate::project::{Project, Selection};
+use crate::run::{Prepared, Session};
+use crate::utils::Cancel;
+
+/// A project of the workspace, with its own options.
+pub struct Member {
+    pub root: PathBuf,
+    pub make_options: crate::make::Options,
+    pub run_options: crate::run::Options,
+}
+
+/// Directories that are never searched for projects.
+const SKIPPED_DIRS: [&str; 2] = ["elm-stuff", "node_modules"];
+
+/// Find the root directories of all Elm projects within `dir`.
+pub fn discover(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = crate::utils::absolute_path(dir)?;
+    let is_skipped = |name: &str| name.starts_with('.') || SKIPPED_DIRS.contains(&name);
+    let mut roots: Vec<PathBuf> = WalkDir::new(&dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_str().map_or(true, is_skipped))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && e.file_name() == "elm.json")
+        .filter_map(|e| e.path().parent().map(Path::to_path_buf))
+        .collect();
+    roots.sort();
+    if roots.is_empty() {
+        anyhow::bail!("No Elm project was found in {}", dir.display());
+    }
+    Ok(roots)
+}
+
+impl Member {
+    /// Whether one of the tests directories of the project exists.
+    pub fn has_tests_dir(&self) -> bool {
+        let tests_dirs = &self.make_options.tests_dirs;
+        tests_dirs.iter().any(|dir| self.root.join(dir).is_dir())
+    }
+}
+
+/// Prepare every member with `prepare_one`, then call `run_one` on each of them in order.
+///
+/// Members are prepared in parallel only when offline, since no package is downloaded then.
+/// Otherwise concurrent downloads could corrupt the shared ELM_HOME.
+/// Runs are sequential to keep the outputs of projects separate.
+fn orchestrate<T: Send>(
+    members: &[Member],
+    prepare_one: impl Fn(&Member) -> anyhow::Result<T> + Sync,
+    mut run_one: impl FnMut(&Member, T) -> anyhow::Result<i32>,
+) -> i32 {
+    let parallel = members
+        .iter()
+        .all(|m| matches!(m.make_options.connectivity, ConnectivityStrategy::Offline));
+    let prepared: Vec<anyhow::Result<T>> = if parallel {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = members
+                .iter()
+                .map(|member| scope.spawn(|| prepare_one(member)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow::anyhow!("Preparing the tests panicked")))
+                })
+                .collect()
+        })
+    } else {
+        members.iter().map(&prepare_one).collect()
+    };
+
+    let mut outcomes = Vec::new();
+    for (member, prepared) in members.iter().zip(prepared) {
+        let title = format!("Project {}", member.root.display());
+        log::error!("\n{}\n{}\n", title, "=".repeat(title.len()));
+        let outcome = prepared.and_then(|p| run_one(member, p));
+        if let Err(err) = &outcome {
+            log::error!("Error: {err:?}");
+        }
+        outcomes.push(outcome);
+    }
+    print_summary(members, &outcomes)
+}
+
+/// Print the outcome of every project and return the combined exit code:
+/// the highest exit code of all projects, where an error counts as 1.
+fn print_summary(members: &[Member], outcomes: &[anyhow::Result<i32>]) -> i32 {
+    log::error!("\nSummary of {} projects:", members.len());
+    let mut exit_code = 0;
+    for (member, outcome) in members.iter().zip(outcomes) {
+        let (code, status) = match outcome {
+            Ok(0) => (0, "✓ passed".to_string()),
+            Ok(code) => (*code, format!("✗ failed (exit code {code})")),
+            Err(err) => (1, format!("✗ error: {err}")),
+        };
+        log::error!("  {status}  {}", member.root.display());
+        exit_code = exit_code.max(code);
+    }
+    exit_code
+}
+
+/// Compile the tests of all members.
+pub fn make(elm_home: &Path, members: &[Member]) -> anyhow::Result<i32> {
+    let prepare_one = |member: &Member| {
+        let project = Project::from_dir(&member.root, &member.make_options.tests_dirs)?;
+        let selection = Selection::default();
+        let cancel = Cancel::default();
+        crate::make::main_helper(
+            elm_home,
+            &project,
+            &member.make_options,
+            &selection,
+            &cancel,
+        )
+    };
+    let run_one = |_: &Member, output| match output {
+        Output::MakeFailure => Ok(1),
+        Output::MakeSuccess { .. } | Output::NoSelectedTests => Ok(0),
+    };
+    Ok(orchestrate(members, prepare_one, run_one))
+}
+
+/// Compile and run the tests of all members.
+pub fn run(elm_home: &Path, members: &[Member]) -> anyhow::Result<i32> {
+    if members.iter().any(|m| m.make_options.watch) {
+        anyhow::bail!("--watch is not supported with multiple projects");
+    }
+    let prepare_one = |member: &Member| {
+        let runtime =
+            crate::runtime::detect(member.run_options.runtime, &member.run_options.executables)?;
+        let project = Project::from_dir(&member.root, &member.make_options.tests_dirs)?;
+        crate::run::prepare(
+            elm_home,
+            &project,
+            &Selection::default(),
+            &Cancel::default(),
+            &member.make_options,
+            &member.run_options,
+            &runtime,
+        )
+    };
+    let run_one = |member: &Member, prepared: Prepared| {
+        let cancel = Cancel::default();
+        crate::run::run_prepared(prepared, &member.run_options, &cancel, &mut Session::new())
+    };
+    Ok(orchestrate(members, prepare_one, run_one))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::TestDir;
+    use std::path::PathBuf;
+
+    #[test]
+    fn discover() {
+        let test_dir = TestDir::new();
+        let dir = test_dir.path().to_path_buf();
+        let project = |path: &str, with_tests: bool| {
+            let elm_json = test_dir.write(&format!("{path}/elm.json"), "{}");
+            if with_tests {
+                std::fs::create_dir_all(elm_json.with_file_name("tests")).unwrap();
+            }
+        };
+        project("apps/a", true);
+        project("apps/b", false);
+        project("packages/c", true);
+        project("packages/c/elm-stuff/tests-0.19.1", true);
+        project("node_modules/d", true);
+        project(".hidden/e", true);
+        let roots = super::discover(&dir).unwrap();
+        let expected: Vec<PathBuf> = ["apps/a", "apps/b", "packages/c"]
+            .iter()
+            .map(|p| dir.join(p))
+            .collect();
+        assert_eq!(roots, expected);
+        drop(test_dir);
+        assert!(super::discover(&dir).is_err());
+    }
+}
@@ -28,12 +28,12 @@ check =
     checkHelperReplaceMe___
 
 
-checkHelperReplaceMe___ : a -> b
+{-| Placeholder implementation, without Debug.todo to be compatible with --optimize.
+The kernel patch fails if it cannot replace it.
+-}
+checkHelperReplaceMe___ : a -> Maybe Test
 checkHelperReplaceMe___ _ =
-    Debug.todo """The regex for replacing this Debug.todo with some real code must have failed since you see this message!
-
-Please report this bug: https://github.com/mpizenberg/elm-test-rs/issues/new
-"""
+    Nothing
 
 
 tests : List Test
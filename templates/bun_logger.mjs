@@ -0,0 +1,2 @@
+export const logs = [];
+console.elmlog = (str) => logs.push(str + "\n");
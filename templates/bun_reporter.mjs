@@ -0,0 +1,45 @@
+import readline from "node:readline";
+
+// From templates/polyfills.js
+{{ polyfills }}
+
+// Compiled by elm-test-rs from templates/Reporter.elm
+import { Elm } from "./Reporter.elm.js";
+
+// Create a long lived reporter
+const flags = {
+  initialSeed: {{ initialSeed }},
+  fuzzRuns: {{ fuzzRuns }},
+  mode: "{{ reporter }}",
+  verbosity: {{ verbosity }},
+  globs: {{ globs }},
+  paths: {{ paths }},
+};
+const reporter = Elm.Reporter.init({ flags: flags });
+
+// Messages to the supervisor are JSON lines on stdout
+function send(msg) {
+  process.stdout.write(JSON.stringify(msg) + "\n");
+}
+
+// Forward the Elm stdout port to the supervisor
+reporter.ports.stdout.subscribe((str) => send({ type_: "stdout", content: str }));
+
+// Tell the supervisor when the reporter has finished
+reporter.ports.signalFinished.subscribe(({ exitCode, testsCount }) => {
+  send({ type_: "finished", exitCode: exitCode });
+});
+
+// Results forwarded by the supervisor from the runners, as JSON lines on stdin
+const rl = readline.createInterface({ input: process.stdin });
+rl.on("line", (line) => {
+  const msg = JSON.parse(line);
+  if (msg.type_ == "testsCount") {
+    reporter.ports.restart.send(msg);
+  } else if (msg.type_ == "testResult") {
+    reporter.ports.incomingResult.send(msg);
+  } else {
+    console.error("Invalid supervisor msg.type_:", msg.type_);
+  }
+});
+rl.on("close", () => process.exit(0));
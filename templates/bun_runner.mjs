@@ -0,0 +1,55 @@
+import readline from "node:readline";
+
+// From templates/polyfills.js
+{{ polyfills }}
+
+// Capture Debug.log from elm code
+// which has been kernel-switched to "console.elmlog"
+import { logs } from "./bun_logger.mjs";
+
+// Compiled by elm-test-rs from templates/Runner.elm
+import { Elm } from "./Runner.elm.js";
+
+// Start the Elm app
+const flags = { initialSeed: {{ initialSeed }}, fuzzRuns: {{ fuzzRuns }}, filter: {{ filter }} };
+const app = Elm.Runner.init({ flags: flags });
+
+// Record the timing at which we received the last "runTest" message
+let startTime;
+
+// Messages to the supervisor are JSON lines on stdout
+function send(msg) {
+  process.stdout.write(JSON.stringify(msg) + "\n");
+}
+
+// Communication from Supervisor to Elm runner via port
+// Messages from the supervisor are JSON lines on stdin
+const rl = readline.createInterface({ input: process.stdin });
+rl.on("line", (line) => {
+  const msg = JSON.parse(line);
+  if (msg.type_ == "askTestsCount") {
+    app.ports.askTestsCount.send();
+  } else if (msg.type_ == "runTest") {
+    startTime = performance.now();
+    app.ports.receiveRunTest.send(msg.id);
+  } else {
+    console.error("Invalid supervisor msg.type_:", msg.type_);
+  }
+});
+rl.on("close", () => process.exit(0));
+
+// Communication from Elm runner to Supervisor via port
+// Subscribe to outgoing Elm ports defined in templates/Runner.elm
+app.ports.sendResult.subscribe((msg) => {
+  msg.type_ = "testResult";
+  msg.duration = performance.now() - startTime;
+  msg.logs = logs;
+  send(msg);
+  logs.length = 0;
+});
+app.ports.sendTestsCount.subscribe((msg) => {
+  msg.type_ = "testsCount";
+  msg.logs = logs;
+  send(msg);
+  logs.length = 0;
+});
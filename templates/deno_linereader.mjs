@@ -1,12 +1,12 @@
-const _readTillDone = async (rid, text = "") => {
+const _readTillDone = async (reader, text = "") => {
   const decoder = new TextDecoder();
   let buf = new Uint8Array(100);
   let indexOfLine = text.indexOf("\n");
   if (indexOfLine === -1) {
-    const num = await Deno.read(rid, buf);
+    const num = await reader.read(buf);
     if (num) {
       text = text + decoder.decode(buf.slice(0, num));
-      return _readTillDone(rid, text);
+      return _readTillDone(reader, text);
     } else {
       return [text, "", true];
     }
@@ -15,14 +15,14 @@ const _readTillDone = async (rid, text = "") => {
   }
 };
 
-export const readLine = async (rid) => {
+export const readLine = async (reader) => {
   let text = "";
   let done = false;
   return {
     [Symbol.asyncIterator]() {
       return {
         async next() {
-          const [t, rest, d] = await _readTillDone(rid, text);
+          const [t, rest, d] = await _readTillDone(reader, text);
           if (done) {
             return { done: true, value: t };
           } else {
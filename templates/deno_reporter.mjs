@@ -0,0 +1,50 @@
+// From templates/polyfills.js
+{{ polyfills }}
+
+import { readLine } from "./deno_linereader.mjs";
+
+// Compiled by elm-test-rs from templates/Reporter.elm
+import { Elm } from "./Reporter.elm.js";
+
+// Create a long lived reporter
+const flags = {
+  initialSeed: {{ initialSeed }},
+  fuzzRuns: {{ fuzzRuns }},
+  mode: "{{ reporter }}",
+  verbosity: {{ verbosity }},
+  globs: {{ globs }},
+  paths: {{ paths }},
+};
+const reporter = Elm.Reporter.init({ flags: flags });
+
+// Messages to the supervisor are JSON lines on stdout
+const encoder = new TextEncoder();
+function send(msg) {
+  const bytes = encoder.encode(JSON.stringify(msg) + "\n");
+  // writeSync may only write part of the bytes
+  for (let written = 0; written < bytes.length; ) {
+    written += Deno.stdout.writeSync(bytes.subarray(written));
+  }
+}
+
+// Forward the Elm stdout port to the supervisor
+reporter.ports.stdout.subscribe((str) => send({ type_: "stdout", content: str }));
+
+// Tell the supervisor when the reporter has finished
+reporter.ports.signalFinished.subscribe(({ exitCode, testsCount }) => {
+  send({ type_: "finished", exitCode: exitCode });
+});
+
+// Results forwarded by the supervisor from the runners, as JSON lines on stdin
+for await (const line of await readLine(Deno.stdin)) {
+  if (line == "") continue;
+  const msg = JSON.parse(line);
+  if (msg.type_ == "testsCount") {
+    reporter.ports.restart.send(msg);
+  } else if (msg.type_ == "testResult") {
+    reporter.ports.incomingResult.send(msg);
+  } else {
+    console.error("Invalid supervisor msg.type_:", msg.type_);
+  }
+}
+Deno.exit(0);
@@ -1,6 +1,8 @@
 // From templates/polyfills.js
 {{ polyfills }}
 
+import { readLine } from "./deno_linereader.mjs";
+
 // Capture Debug.log from elm code
 // which has been kernel-switched to "console.elmlog"
 import { logs } from "./deno_logger.mjs";
@@ -15,17 +17,15 @@ const app = Elm.Runner.init({ flags: flags });
 // Record the timing at which we received the last "runTest" message
 let startTime;
 
-// Communication from Supervisor to Elm runner via port
-self.onmessage = (msg) => {
-  if (msg.data.type_ == "askTestsCount") {
-    app.ports.askTestsCount.send();
-  } else if (msg.data.type_ == "runTest") {
-    startTime = performance.now();
-    app.ports.receiveRunTest.send(msg.data.id);
-  } else {
-    console.error("Invalid supervisor msg.type_:", msg.data.type_);
+// Messages to the supervisor are JSON lines on stdout
+const encoder = new TextEncoder();
+function send(msg) {
+  const bytes = encoder.encode(JSON.stringify(msg) + "\n");
+  // writeSync may only write part of the bytes
+  for (let written = 0; written < bytes.length; ) {
+    written += Deno.stdout.writeSync(bytes.subarray(written));
   }
-};
+}
 
 // Communication from Elm runner to Supervisor via port
 // Subscribe to outgoing Elm ports defined in templates/Runner.elm
@@ -33,12 +33,28 @@ app.ports.sendResult.subscribe((msg) => {
   msg.type_ = "testResult";
   msg.duration = performance.now() - startTime;
   msg.logs = logs;
-  self.postMessage(msg);
+  send(msg);
   logs.length = 0;
 });
 app.ports.sendTestsCount.subscribe((msg) => {
   msg.type_ = "testsCount";
   msg.logs = logs;
-  self.postMessage(msg);
+  send(msg);
   logs.length = 0;
 });
+
+// Communication from Supervisor to Elm runner via port
+// Messages from the supervisor are JSON lines on stdin
+for await (const line of await readLine(Deno.stdin)) {
+  if (line == "") continue;
+  const msg = JSON.parse(line);
+  if (msg.type_ == "askTestsCount") {
+    app.ports.askTestsCount.send();
+  } else if (msg.type_ == "runTest") {
+    startTime = performance.now();
+    app.ports.receiveRunTest.send(msg.id);
+  } else {
+    console.error("Invalid supervisor msg.type_:", msg.type_);
+  }
+}
+Deno.exit(0);
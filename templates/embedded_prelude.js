@@ -0,0 +1,37 @@
+// Minimal environment for compiled Elm code running in the embedded QuickJS engine.
+// The __stdout and __stderr functions are provided by elm-test-rs.
+globalThis.console = {
+  log: (...args) => __stdout(args.join(" ") + "\n"),
+  info: (...args) => __stdout(args.join(" ") + "\n"),
+  warn: (...args) => __stderr(args.join(" ") + "\n"),
+  error: (...args) => __stderr(args.join(" ") + "\n"),
+};
+
+globalThis.performance = { now: () => Date.now() };
+
+// Timers are run by elm-test-rs when there is no other pending work.
+let __timers = [];
+let __timersCount = 0;
+
+globalThis.setTimeout = (callback, delay, ...args) => {
+  __timersCount += 1;
+  __timers.push({ id: __timersCount, at: Date.now() + (delay || 0), callback, args });
+  return __timersCount;
+};
+
+globalThis.clearTimeout = (id) => {
+  __timers = __timers.filter((timer) => timer.id !== id);
+};
+
+// Run all due timers and return the delay until the next one (-1 if none).
+globalThis.__runTimers = () => {
+  __timers.sort((a, b) => a.at - b.at || a.id - b.id);
+  while (__timers.length > 0 && __timers[0].at <= Date.now()) {
+    const timer = __timers.shift();
+    timer.callback(...timer.args);
+  }
+  return __timers.length > 0 ? Math.max(0, __timers[0].at - Date.now()) : -1;
+};
+
+// From templates/polyfills.js
+{{ polyfills }}
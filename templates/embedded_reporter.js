@@ -0,0 +1,33 @@
+// Evaluated by elm-test-rs right after Reporter.elm.js in its own thread.
+// The __send function is provided by elm-test-rs.
+
+// Create a long lived reporter
+const flags = {
+  initialSeed: {{ initialSeed }},
+  fuzzRuns: {{ fuzzRuns }},
+  mode: "{{ reporter }}",
+  verbosity: {{ verbosity }},
+  globs: {{ globs }},
+  paths: {{ paths }},
+};
+const reporter = Elm.Reporter.init({ flags: flags });
+
+// Forward the Elm stdout port to the supervisor
+reporter.ports.stdout.subscribe((str) => __send(JSON.stringify({ type_: "stdout", content: str })));
+
+// Tell the supervisor when the reporter has finished
+reporter.ports.signalFinished.subscribe(({ exitCode, testsCount }) => {
+  __send(JSON.stringify({ type_: "finished", exitCode: exitCode }));
+});
+
+// Results forwarded by the supervisor from the runners
+globalThis.__receive = (str) => {
+  const msg = JSON.parse(str);
+  if (msg.type_ == "testsCount") {
+    reporter.ports.restart.send(msg);
+  } else if (msg.type_ == "testResult") {
+    reporter.ports.incomingResult.send(msg);
+  } else {
+    console.error("Invalid supervisor msg.type_:", msg.type_);
+  }
+};
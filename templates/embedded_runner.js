@@ -0,0 +1,43 @@
+// Evaluated by elm-test-rs right after Runner.elm.js in each worker thread.
+// The __send function is provided by elm-test-rs.
+
+// Capture Debug.log from elm code
+// which has been kernel-switched to "console.elmlog"
+const logs = [];
+console.elmlog = (str) => logs.push(str + "\n");
+
+// Start the Elm app compiled by elm-test-rs from templates/Runner.elm
+const flags = { initialSeed: {{ initialSeed }}, fuzzRuns: {{ fuzzRuns }}, filter: {{ filter }} };
+const app = Elm.Runner.init({ flags: flags });
+
+// Record the timing at which we received the last "runTest" message
+let startTime;
+
+// Communication from Supervisor to Elm runner via port
+globalThis.__receive = (str) => {
+  const msg = JSON.parse(str);
+  if (msg.type_ == "askTestsCount") {
+    app.ports.askTestsCount.send();
+  } else if (msg.type_ == "runTest") {
+    startTime = performance.now();
+    app.ports.receiveRunTest.send(msg.id);
+  } else {
+    console.error("Invalid supervisor msg.type_:", msg.type_);
+  }
+};
+
+// Communication from Elm runner to Supervisor via port
+// Subscribe to outgoing Elm ports defined in templates/Runner.elm
+app.ports.sendResult.subscribe((msg) => {
+  msg.type_ = "testResult";
+  msg.duration = performance.now() - startTime;
+  msg.logs = logs;
+  __send(JSON.stringify(msg));
+  logs.length = 0;
+});
+app.ports.sendTestsCount.subscribe((msg) => {
+  msg.type_ = "testsCount";
+  msg.logs = logs;
+  __send(JSON.stringify(msg));
+  logs.length = 0;
+});
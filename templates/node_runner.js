@@ -1,10 +1,11 @@
-const { parentPort } = require("worker_threads");
+const readline = require("readline");
 const { performance } = require("perf_hooks");
 
 // From templates/polyfills.js
 {{ polyfills }}
 
 // Capture Debug.log from elm code
+// which has been kernel-switched to "console.elmlog"
 let logs = [];
 console.elmlog = (str) => logs.push(str + "\n");
 
@@ -18,8 +19,16 @@ const app = Elm.Runner.init({ flags: flags });
 // Record the timing at which we received the last "runTest" message
 let startTime;
 
+// Messages to the supervisor are JSON lines on stdout
+function send(msg) {
+  process.stdout.write(JSON.stringify(msg) + "\n");
+}
+
 // Communication from Supervisor to Elm runner via port
-parentPort.on("message", (msg) => {
+// Messages from the supervisor are JSON lines on stdin
+const rl = readline.createInterface({ input: process.stdin });
+rl.on("line", (line) => {
+  const msg = JSON.parse(line);
   if (msg.type_ == "askTestsCount") {
     app.ports.askTestsCount.send();
   } else if (msg.type_ == "runTest") {
@@ -29,6 +38,7 @@ parentPort.on("message", (msg) => {
     console.error("Invalid supervisor msg.type_:", msg.type_);
   }
 });
+rl.on("close", () => process.exit(0));
 
 // Communication from Elm runner to Supervisor via port
 // Subscribe to outgoing Elm ports defined in templates/Runner.elm
@@ -36,12 +46,12 @@ app.ports.sendResult.subscribe((msg) => {
   msg.type_ = "testResult";
   msg.duration = performance.now() - startTime;
   msg.logs = logs;
-  parentPort.postMessage(msg);
+  send(msg);
   logs.length = 0;
 });
 app.ports.sendTestsCount.subscribe((msg) => {
   msg.type_ = "testsCount";
   msg.logs = logs;
-  parentPort.postMessage(msg);
+  send(msg);
   logs.length = 0;
 });
@@ -41,20 +41,24 @@ fn check_all_failing() {
     }
 }
 
-#[cfg(not(feature = "deno"))]
-fn check_example(project_dir: &Path, exit_code: i32) {
-    let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
-    let assert = cmd.current_dir(project_dir).arg("-vvv").assert();
-    assert.code(exit_code);
-}
+/// The runtime used to run the example projects, selected with cargo features.
+const RUNTIME: &str = if cfg!(feature = "deno") {
+    "deno"
+} else if cfg!(feature = "bun") {
+    "bun"
+} else if cfg!(feature = "embedded") {
+    "embedded"
+} else {
+    "node"
+};
 
-#[cfg(feature = "deno")]
 fn check_example(project_dir: &Path, exit_code: i32) {
     let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
     let assert = cmd
         .current_dir(project_dir)
         .arg("-vvv")
-        .arg("--deno")
+        .arg("--runtime")
+        .arg(RUNTIME)
         .assert();
     assert.code(exit_code);
 }
@@ -75,3 +79,230 @@ fn check_arg_project() {
     let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
     cmd.arg("--project").arg(pkg).arg("-vvv").assert().success();
 }
+
+// -------------------------------------------------------------------
+// Testing the selection of tests and the other CLI options
+// -------------------------------------------------------------------
+
+/// Copy an example project of tests/example-projects/options into a temporary directory,
+/// so that each test compiles it in its own elm-stuff.
+fn copy_options_example(name: &str) -> tempfile::TempDir {
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let path = entry.unwrap().path();
+            let target = to.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy_dir(&path, &target);
+            } else {
+                std::fs::copy(&path, &target).unwrap();
+            }
+        }
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let example = Path::new("tests").join("example-projects").join("options");
+    copy_dir(&example.join(name), dir.path());
+    dir
+}
+
+/// Run elm-test-rs with the JSON reporter in the project, and return its stdout and stderr.
+fn run_example(project_dir: &Path, args: &[&str], exit_code: i32) -> (String, String) {
+    let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
+    let output = cmd
+        .current_dir(project_dir)
+        .arg("-vv")
+        .arg("--runtime")
+        .arg(RUNTIME)
+        .arg("--report")
+        .arg("json")
+        .args(args)
+        .assert()
+        .code(exit_code)
+        .get_output()
+        .clone();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// Check that exactly the given tests of the selection example were run.
+fn assert_ran(stdout: &str, tests: &[&str]) {
+    let all = [
+        "first test",
+        "second test",
+        "unexposed test",
+        "failing test",
+        "logged test",
+        "spec test",
+        "colocated test",
+    ];
+    for test in all {
+        assert_eq!(
+            stdout.contains(test),
+            tests.contains(&test),
+            "{test}:\n{stdout}"
+        );
+    }
+}
+
+#[test]
+fn check_default_selection() {
+    let project = copy_options_example("selection");
+    let (stdout, stderr) = run_example(project.path(), &[], 2);
+    assert_ran(
+        &stdout,
+        &["first test", "second test", "failing test", "logged test"],
+    );
+    let warning = "unexposed is a Test but is not exposed by Passing";
+    assert!(stderr.contains(warning), "{stderr}");
+}
+
+#[test]
+fn check_arg_ignore() {
+    let project = copy_options_example("selection");
+    let (stdout, _) = run_example(project.path(), &["--ignore", "tests/Failing.elm"], 0);
+    assert_ran(&stdout, &["first test", "second test", "logged test"]);
+}
+
+#[test]
+fn check_arg_value_selection() {
+    let project = copy_options_example("selection");
+    let (stdout, _) = run_example(project.path(), &["tests/Passing.elm::first"], 0);
+    assert_ran(&stdout, &["first test"]);
+}
+
+#[test]
+fn check_arg_tests_dir() {
+    let project = copy_options_example("selection");
+    let (stdout, _) = run_example(project.path(), &["--tests-dir", "spec"], 0);
+    assert_ran(&stdout, &["spec test"]);
+}
+
+#[test]
+fn check_arg_colocated() {
+    let project = copy_options_example("selection");
+    let args = ["--colocated", "--ignore", "tests/Failing.elm"];
+    let (stdout, _) = run_example(project.path(), &args, 0);
+    assert_ran(
+        &stdout,
+        &["first test", "second test", "logged test", "colocated test"],
+    );
+}
+
+#[test]
+fn check_arg_strict() {
+    let project = copy_options_example("selection");
+    let (stdout, stderr) = run_example(project.path(), &["--strict"], 1);
+    assert_ran(&stdout, &[]);
+    assert!(stderr.contains("Some tests are not exposed"), "{stderr}");
+    let warning = "unexposed is a Test but is not exposed by Passing";
+    assert!(stderr.contains(warning), "{stderr}");
+}
+
+#[test]
+fn check_arg_compile_mode() {
+    let project = copy_options_example("selection");
+    // The Debug module cannot be used with --optimize.
+    run_example(project.path(), &["--compile-mode", "optimize"], 1);
+    let args = ["--compile-mode", "optimize", "tests/Passing.elm"];
+    let (stdout, _) = run_example(project.path(), &args, 0);
+    assert_ran(&stdout, &["first test", "second test"]);
+    let args = ["--compile-mode", "debug", "tests/Debugging.elm"];
+    let (stdout, _) = run_example(project.path(), &args, 0);
+    assert_ran(&stdout, &["logged test"]);
+}
+
+#[test]
+fn check_compilation_cache() {
+    let project = copy_options_example("selection");
+    let (_, stderr) = run_example(project.path(), &["tests/Passing.elm"], 0);
+    assert!(stderr.contains("Compiling Reporter.elm.js"), "{stderr}");
+    let (stdout, stderr) = run_example(project.path(), &["tests/Passing.elm"], 0);
+    assert_ran(&stdout, &["first test", "second test"]);
+    assert!(
+        stderr.contains("Tests modules are unchanged since their last compilation"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Reporter.elm.js is up to date"), "{stderr}");
+    // Another selection of tests compiles the runner again, but not the reporter.
+    let (stdout, stderr) = run_example(project.path(), &["tests/Passing.elm::second"], 0);
+    assert_ran(&stdout, &["second test"]);
+    assert!(
+        !stderr.contains("Tests modules are unchanged since their last compilation"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Reporter.elm.js is up to date"), "{stderr}");
+}
+
+// -------------------------------------------------------------------
+// Testing the supervisor of the runners
+// -------------------------------------------------------------------
+
+#[test]
+fn check_supervisor_workers() {
+    let project = copy_options_example("selection");
+    for workers in ["1", "4"] {
+        let (stdout, _) = run_example(project.path(), &["--workers", workers], 2);
+        assert_ran(
+            &stdout,
+            &["first test", "second test", "failing test", "logged test"],
+        );
+    }
+}
+
+#[test]
+fn check_supervisor_timeout() {
+    let project = copy_options_example("timeout");
+    let (_, stderr) = run_example(project.path(), &["--timeout", "2"], 1);
+    assert!(stderr.contains("did not finish within 2 s"), "{stderr}");
+}
+
+// -------------------------------------------------------------------
+// Testing the selection of the runtime
+// -------------------------------------------------------------------
+
+#[test]
+fn check_conflicting_runtime_paths() {
+    let app = Path::new("tests")
+        .join("example-projects")
+        .join("passing")
+        .join("app");
+    let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
+    cmd.current_dir(app)
+        .args(["--deno-path", "deno", "--bun-path", "bun"])
+        .assert()
+        .code(1);
+}
+
+/// A custom path of the runtime executable selects that runtime.
+#[cfg(any(feature = "deno", feature = "bun"))]
+#[test]
+fn check_runtime_path() {
+    let app = Path::new("tests")
+        .join("example-projects")
+        .join("passing")
+        .join("app");
+    let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
+    cmd.current_dir(app)
+        .arg(format!("--{RUNTIME}-path"))
+        .arg(RUNTIME)
+        .assert()
+        .success();
+}
+
+/// The embedded runtime interrupts a test that never finishes, instead of blocking its thread.
+#[cfg(feature = "embedded")]
+#[test]
+fn check_embedded_runtime_interrupt() {
+    let timeout = Path::new("tests")
+        .join("example-projects")
+        .join("options")
+        .join("timeout");
+    let mut cmd = Command::cargo_bin("elm-test-rs").unwrap();
+    cmd.current_dir(timeout)
+        .args(["--runtime", "embedded", "--timeout", "2"])
+        .timeout(std::time::Duration::from_secs(120))
+        .assert()
+        .code(1);
+}
@@ -0,0 +1,9 @@
+module Spec exposing (spec)
+
+import Expect
+import Test exposing (Test)
+
+
+spec : Test
+spec =
+    Test.test "spec test" (\_ -> Expect.pass)
@@ -0,0 +1,9 @@
+module MainTest exposing (colocated)
+
+import Expect
+import Test exposing (Test)
+
+
+colocated : Test
+colocated =
+    Test.test "colocated test" (\_ -> Expect.pass)
@@ -0,0 +1,9 @@
+module Debugging exposing (logged)
+
+import Expect
+import Test exposing (Test)
+
+
+logged : Test
+logged =
+    Test.test "logged test" (\_ -> Expect.equal 1 (Debug.log "value" 1))
@@ -0,0 +1,9 @@
+module Failing exposing (failing)
+
+import Expect
+import Test exposing (Test)
+
+
+failing : Test
+failing =
+    Test.test "failing test" (\_ -> Expect.fail "This test should fail")
@@ -0,0 +1,19 @@
+module Passing exposing (first, second)
+
+import Expect
+import Test exposing (Test)
+
+
+first : Test
+first =
+    Test.test "first test" (\_ -> Expect.pass)
+
+
+second : Test
+second =
+    Test.test "second test" (\_ -> Expect.pass)
+
+
+unexposed : Test
+unexposed =
+    Test.test "unexposed test" (\_ -> Expect.pass)
@@ -0,0 +1,21 @@
+module Tests exposing (suite)
+
+import Expect
+import Test exposing (Test)
+
+
+suite : Test
+suite =
+    Test.describe "Timeout"
+        [ Test.test "This test should pass" (\_ -> Expect.pass)
+        , Test.test "This test never finishes" (\_ -> Expect.equal 0 (loop 1))
+        ]
+
+
+loop : Int -> Int
+loop n =
+    if n > 0 then
+        loop (n + 1)
+
+    else
+        n
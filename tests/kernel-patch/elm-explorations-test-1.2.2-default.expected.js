@@ -0,0 +1,44 @@
+const __elmTestSymbol = Symbol('elmTestSymbol');
+(function(scope){
+'use strict';
+
+function F(arity, fun, wrapper) {
+  wrapper.a = arity;
+  wrapper.f = fun;
+  return wrapper;
+}
+
+function F2(fun) {
+  return F(2, fun, function(a) { return function(b) { return fun(a,b); }; })
+}
+
+('Compiled in DEV mode. Follow the advice at https://elm-lang.org/0.19.1/optimize for better performance and smaller assets.');
+
+var $elm$core$Maybe$Just = function (a) {
+	return {$: 'Just', a: a};
+};
+var $elm$core$Maybe$Nothing = {$: 'Nothing'};
+var $elm_explorations$test$Test$Internal$Batch = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 'Batch', a: a};
+};
+var $elm_explorations$test$Test$Internal$FuzzTest = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 'FuzzTest', a: a};
+};
+var $elm_explorations$test$Test$Internal$Labeled = F2(
+	function (a, b) {
+		return { __elmTestSymbol: __elmTestSymbol,$: 'Labeled', a: a, b: b};
+	});
+var $elm_explorations$test$Test$Internal$Only = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 'Only', a: a};
+};
+var $elm_explorations$test$Test$Internal$Skipped = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 'Skipped', a: a};
+};
+var $elm_explorations$test$Test$Internal$UnitTest = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 'UnitTest', a: a};
+};
+var $author$project$Runner$checkHelperReplaceMe___ = function (_v0) {
+	return $elm$core$Maybe$Nothing;
+};
+var $author$project$Runner$check = value => value && value.__elmTestSymbol === __elmTestSymbol ? $elm$core$Maybe$Just(value) : $elm$core$Maybe$Nothing;
+_Platform_export({'Runner':{'init':$author$project$Runner$main}});}(this));
@@ -0,0 +1,43 @@
+(function(scope){
+'use strict';
+
+function F(arity, fun, wrapper) {
+  wrapper.a = arity;
+  wrapper.f = fun;
+  return wrapper;
+}
+
+function F2(fun) {
+  return F(2, fun, function(a) { return function(b) { return fun(a,b); }; })
+}
+
+console.warn('Compiled in DEV mode. Follow the advice at https://elm-lang.org/0.19.1/optimize for better performance and smaller assets.');
+
+var $elm$core$Maybe$Just = function (a) {
+	return {$: 'Just', a: a};
+};
+var $elm$core$Maybe$Nothing = {$: 'Nothing'};
+var $elm_explorations$test$Test$Internal$Batch = function (a) {
+	return {$: 'Batch', a: a};
+};
+var $elm_explorations$test$Test$Internal$FuzzTest = function (a) {
+	return {$: 'FuzzTest', a: a};
+};
+var $elm_explorations$test$Test$Internal$Labeled = F2(
+	function (a, b) {
+		return {$: 'Labeled', a: a, b: b};
+	});
+var $elm_explorations$test$Test$Internal$Only = function (a) {
+	return {$: 'Only', a: a};
+};
+var $elm_explorations$test$Test$Internal$Skipped = function (a) {
+	return {$: 'Skipped', a: a};
+};
+var $elm_explorations$test$Test$Internal$UnitTest = function (a) {
+	return {$: 'UnitTest', a: a};
+};
+var $author$project$Runner$checkHelperReplaceMe___ = function (_v0) {
+	return $elm$core$Maybe$Nothing;
+};
+var $author$project$Runner$check = $author$project$Runner$checkHelperReplaceMe___;
+_Platform_export({'Runner':{'init':$author$project$Runner$main}});}(this));
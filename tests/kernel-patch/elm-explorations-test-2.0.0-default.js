@@ -0,0 +1,60 @@
+(function(scope){
+'use strict';
+
+function F(arity, fun, wrapper) {
+  wrapper.a = arity;
+  wrapper.f = fun;
+  return wrapper;
+}
+
+function F2(fun) {
+  return F(2, fun, function(a) { return function(b) { return fun(a,b); }; })
+}
+
+function A2(fun, a, b) {
+  return fun.a === 2 ? fun.f(a, b) : fun(a)(b);
+}
+
+console.warn('Compiled in DEV mode. Follow the advice at https://elm-lang.org/0.19.1/optimize for better performance and smaller assets.');
+
+
+var _Debug_log = F2(function(tag, value)
+{
+	console.log(tag + ': ' + _Debug_toString(value));
+	return value;
+});
+
+function _Debug_toString(value)
+{
+	return _Debug_toAnsiString(false, value);
+}
+var $elm$core$Maybe$Just = function (a) {
+	return {$: 'Just', a: a};
+};
+var $elm$core$Maybe$Nothing = {$: 'Nothing'};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__Batch = function (a) {
+	return {$: 'ElmTestVariant__Batch', a: a};
+};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__Labeled = F2(
+	function (a, b) {
+		return {$: 'ElmTestVariant__Labeled', a: a, b: b};
+	});
+var $elm_explorations$test$Test$Internal$ElmTestVariant__UnitTest = function (a) {
+	return {$: 'ElmTestVariant__UnitTest', a: a};
+};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__FuzzTest = function (a) {
+	return {$: 'ElmTestVariant__FuzzTest', a: a};
+};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__Skipped = function (a) {
+	return {$: 'ElmTestVariant__Skipped', a: a};
+};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__Only = function (a) {
+	return {$: 'ElmTestVariant__Only', a: a};
+};
+var $elm$core$Debug$log = _Debug_log;
+var $author$project$LogTests$logged = A2($elm$core$Debug$log, 'console.log(tag + \': \' + _Debug_toString(value));', 0);
+var $author$project$Runner$checkHelperReplaceMe___ = function (_v0) {
+	return $elm$core$Maybe$Nothing;
+};
+var $author$project$Runner$check = $author$project$Runner$checkHelperReplaceMe___;
+_Platform_export({'Runner':{'init':$author$project$Runner$main}});}(this));
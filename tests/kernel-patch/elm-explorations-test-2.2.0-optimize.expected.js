@@ -0,0 +1,34 @@
+const __elmTestSymbol = Symbol('elmTestSymbol');
+(function(scope){
+'use strict';
+
+function F(arity, fun, wrapper) {
+  wrapper.a = arity;
+  wrapper.f = fun;
+  return wrapper;
+}
+
+function F2(fun) {
+  return F(2, fun, function(a) { return function(b) { return fun(a,b); }; })
+}
+
+var $elm$core$Maybe$Just = function (a) {
+	return {$: 0, a: a};
+};
+var $elm$core$Maybe$Nothing = {$: 1};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__Batch = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 5, a: a};
+};
+var $elm_explorations$test$Test$Internal$ElmTestVariant__Labeled = F2(
+	function (a, b) {
+		return { __elmTestSymbol: __elmTestSymbol,$: 2, a: a, b: b};
+	});
+var $elm_explorations$test$Test$Internal$ElmTestVariant__UnitTest = function (a) {
+	return { __elmTestSymbol: __elmTestSymbol,$: 0, a: a};
+};
+var $author$project$WarnTests$message = 'console.warn is not called';
+var $author$project$Runner$checkHelperReplaceMe___ = function (_v0) {
+	return $elm$core$Maybe$Nothing;
+};
+var $author$project$Runner$check = value => value && value.__elmTestSymbol === __elmTestSymbol ? $elm$core$Maybe$Just(value) : $elm$core$Maybe$Nothing;
+_Platform_export({'Runner':{'init':$author$project$Runner$main}});}(this));
@@ -0,0 +1,24 @@
+#!/bin/sh
+# Compile the tests of project/ with each supported version of elm-explorations/test
+# and each compilation mode, and write the Runner.elm.js files and their kernel patch.
+# Requires elm, and network access to download the packages the first time.
+set -e
+fixtures=$(cd "$(dirname "$0")" && pwd)
+repo=$(cd "$fixtures/../.." && pwd)
+cargo build --quiet --manifest-path "$repo/Cargo.toml"
+rm -f "$fixtures"/elm-explorations-test-*.js
+for version in 1.2.2 2.0.0 2.2.0; do
+    for mode in default optimize debug; do
+        work=$(mktemp -d)
+        cp -R "$fixtures/project/." "$work"
+        sed -e "s|\"elm-explorations/test\": \"[^\"]*\"|\"elm-explorations/test\": \"$version\"|" \
+            "$fixtures/project/elm.json" >"$work/elm.json"
+        # The Debug module cannot be used with --optimize.
+        files="tests/LogTests.elm tests/WarnTests.elm"
+        if [ "$mode" = optimize ]; then files=tests/WarnTests.elm; fi
+        (cd "$work" && "$repo/target/debug/elm-test-rs" make --compile-mode "$mode" $files)
+        cp "$work"/elm-stuff/tests-*/js/Runner.elm.js "$fixtures/elm-explorations-test-$version-$mode.js"
+        rm -rf "$work"
+    done
+done
+cd "$repo" && UPDATE_EXPECTED=1 cargo test kernel_patch
@@ -0,0 +1,16 @@
+module LogTests exposing (suite)
+
+import Expect
+import Test exposing (Test)
+
+
+{-| Logged message looking like the definition of Debug.log, left untouched by the kernel patch.
+-}
+logged : Int
+logged =
+    Debug.log "console.log(tag + ': ' + _Debug_toString(value));" 0
+
+
+suite : Test
+suite =
+    Test.test "Debug.log" (\_ -> Expect.equal 0 logged)
@@ -0,0 +1,16 @@
+module WarnTests exposing (suite)
+
+import Expect
+import Test exposing (Test)
+
+
+{-| Test description looking like a call to console.warn, left untouched by the kernel patch.
+-}
+message : String
+message =
+    "console.warn is not called"
+
+
+suite : Test
+suite =
+    Test.test message (\_ -> Expect.pass)
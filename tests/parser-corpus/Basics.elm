@@ -0,0 +1,89 @@
+module Basics exposing
+    ( Int, Float
+    , (+), (-), (*), (/), (//), (^)
+    , (==), (/=), (<), (>), (<=), (>=), max, min
+    , (&&), (||), not
+    , (++)
+    , identity, always, (<|), (|>), (<<), (>>)
+    )
+
+{-| Core functions, in the style of elm/core.
+
+@docs Int, Float, (+), (-)
+-}
+
+import Elm.Kernel.Basics
+
+
+infix right 0 (<|) = apL
+infix left  0 (|>) = apR
+infix right 2 (||) = or
+infix right 3 (&&) = and
+infix non   4 (==) = eq
+infix non   4 (/=) = neq
+infix non   4 (<)  = lt
+infix non   4 (>)  = gt
+infix non   4 (<=) = le
+infix non   4 (>=) = ge
+infix right 5 (++) = append
+infix left  6 (+)  = add
+infix left  6 (-)  = sub
+infix left  7 (*)  = mul
+infix left  7 (/)  = fdiv
+infix left  7 (//) = idiv
+infix right 8 (^)  = pow
+infix left  9 (<<) = composeL
+infix right 9 (>>) = composeR
+
+
+type Int = Int
+
+
+type Float = Float
+
+
+add : number -> number -> number
+add =
+  Elm.Kernel.Basics.add
+
+
+sub : number -> number -> number
+sub =
+  Elm.Kernel.Basics.sub
+
+
+{-| Compare two values, `1 < 2 == True`.
+-}
+max : comparable -> comparable -> comparable
+max x y =
+  if gt x y then x else y
+
+
+min : comparable -> comparable -> comparable
+min x y =
+  if lt x y then x else y
+
+
+not : Bool -> Bool
+not =
+  Elm.Kernel.Basics.not
+
+
+identity : a -> a
+identity x =
+  x
+
+
+always : a -> b -> a
+always a _ =
+  a
+
+
+apL : (a -> b) -> a -> b
+apL f x =
+  f x
+
+
+apR : a -> (a -> b) -> b
+apR x f =
+  f x
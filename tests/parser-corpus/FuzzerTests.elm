@@ -0,0 +1,80 @@
+module FuzzerTests exposing (fuzzerTests, shrinkingTests)
+
+{-| Tests in the style of the elm-explorations/test test suite. -}
+
+import Expect exposing (Expectation)
+import Fuzz exposing (..)
+import Helpers exposing (..)
+import Random
+import Test exposing (..)
+import Test.Runner
+
+
+die : Fuzzer Int
+die =
+    intRange 1 6
+
+
+fuzzerTests : Test
+fuzzerTests =
+    describe "Fuzzer methods that use Debug.crash don't call it"
+        [ describe "FuzzN (uses tupleN) testing string length properties"
+            [ fuzz2 string string "fuzz2" <|
+                \a b ->
+                    testStringLengthIsPreserved [ a, b ]
+            , fuzz3 string string string "fuzz3" <|
+                \a b c ->
+                    testStringLengthIsPreserved [ a, b, c ]
+            ]
+        , fuzz
+            (intRange 1 6)
+            "intRange"
+            (Expect.greaterThan 0)
+        , describe "Whitespace and \"quotes\" -- not a comment"
+            [ test "a {- not a comment -} string" <|
+                \() ->
+                    Expect.equal '{' '{'
+            , test """multiline
+notATest = 1
+{- still a string -}
+""" <|
+                \() ->
+                    Expect.pass
+            ]
+        ]
+
+
+{- A block comment
+hiddenTest : Test
+hiddenTest =
+    {- with a nested one -}
+    test "hidden" <| \() -> Expect.pass
+-}
+
+
+testStringLengthIsPreserved : List String -> Expectation
+testStringLengthIsPreserved strings =
+    strings
+        |> List.map String.length
+        |> List.sum
+        |> Expect.equal (String.length (List.foldl (++) "" strings))
+
+
+shrinkingTests : Test
+shrinkingTests =
+    let
+        seed =
+            Random.initialSeed 42
+
+        innerTest : Test
+        innerTest =
+            test "inner" <| \() -> Expect.pass
+    in
+    testShrinking <|
+        describe "Tests that fail in order to test shrinking"
+            [ fuzz2 int int "Every pair of ints has a zero" <|
+                \i j ->
+                    (i * j)
+                        |> Expect.equal 0
+            , innerTest
+            ]
@@ -0,0 +1,49 @@
+module Helpers exposing (..)
+
+import Expect
+import Test exposing (Test)
+import Test.Runner exposing (Runner, SeededRunners)
+
+
+{-| Helpers of a test suite, exposing everything.
+Values annotated with another type than Test are skipped.
+-}
+expectPass : a -> Expect.Expectation
+expectPass _ =
+    Expect.pass
+
+
+testShrinking : Test -> Test
+testShrinking =
+    Test.Runner.fromTest 100 (Random.initialSeed 1)
+        |> handleSeededRunners
+
+
+type alias Model =
+    { runs : Int
+    , name : String
+    }
+
+
+type Msg
+    = Start
+    | Done (List String)
+
+
+unannotatedSuite =
+    Test.todo "unannotated values are kept as potential tests"
+
+
+qualifiedSuite : Test.Test
+qualifiedSuite =
+    Test.todo "qualified annotation"
+
+
+charSuite : Test
+charSuite =
+    Test.test "chars" <| \() -> Expect.equal '\'' '\''
+
+
+operatorLike : Int
+operatorLike =
+    1 // 2 - 3 -- - 4
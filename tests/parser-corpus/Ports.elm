@@ -0,0 +1,20 @@
+port module Ports exposing
+    ( suite
+    , toJs
+    , fromJs
+    )
+
+import Json.Encode exposing (Value)
+import Test exposing (Test, test)
+
+
+port toJs : Value -> Cmd msg
+
+
+port fromJs : (Value -> msg) -> Sub msg
+
+
+suite : Test
+suite =
+    test "ports are not tests" <|
+        \() -> Expect.pass
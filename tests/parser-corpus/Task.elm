@@ -0,0 +1,66 @@
+effect module Task where { command = MyCmd } exposing
+  ( Task
+  , succeed, fail
+  , map, map2, andThen
+  , perform, attempt
+  )
+
+{-| Tasks make it easy to describe asynchronous operations.
+-}
+
+import Basics exposing (Never, (|>), (<<))
+import Elm.Kernel.Scheduler
+import List exposing ((::))
+import Platform
+import Platform.Cmd exposing (Cmd)
+import Result exposing (Result(..))
+
+
+type alias Task x a =
+  Platform.Task x a
+
+
+succeed : a -> Task x a
+succeed =
+  Elm.Kernel.Scheduler.succeed
+
+
+fail : x -> Task x a
+fail =
+  Elm.Kernel.Scheduler.fail
+
+
+map : (a -> b) -> Task x a -> Task x b
+map func taskA =
+  taskA
+    |> andThen (\a -> succeed (func a))
+
+
+map2 : (a -> b -> result) -> Task x a -> Task x b -> Task x result
+map2 func taskA taskB =
+  taskA
+    |> andThen (\a -> taskB
+    |> andThen (\b -> succeed (func a b)))
+
+
+andThen : (a -> Task x b) -> Task x a -> Task x b
+andThen =
+  Elm.Kernel.Scheduler.andThen
+
+
+type MyCmd msg =
+  Perform (Task Never msg)
+
+
+perform : (a -> msg) -> Task Never a -> Cmd msg
+perform toMessage task =
+  command (Perform (map toMessage task))
+
+
+attempt : (Result x a -> msg) -> Task x a -> Cmd msg
+attempt resultToMessage task =
+  command (Perform (
+    task
+      |> andThen (succeed << resultToMessage << Ok)
+      |> onError (succeed << resultToMessage << Err)
+  ))